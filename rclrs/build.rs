@@ -34,6 +34,13 @@ fn main() {
         }
     }
 
+    // Lets the code follow API changes between ROS distributions, e.g. `#[cfg(ros_distro = "foxy")]`
+    println!("cargo:rerun-if-env-changed=ROS_DISTRO");
+    println!("cargo:rustc-check-cfg=cfg(ros_distro, values(\"foxy\", \"galactic\", \"rolling\"))");
+    if let Ok(ros_distro) = env::var("ROS_DISTRO") {
+        println!("cargo:rustc-cfg=ros_distro=\"{}\"", ros_distro);
+    }
+
     println!("cargo:rustc-link-lib=dylib=rcl");
     println!("cargo:rustc-link-lib=dylib=rcutils");
     println!("cargo:rustc-link-lib=dylib=rmw");
@@ -0,0 +1,71 @@
+use crate::rcl_bindings::*;
+use core::ffi::c_void;
+
+pub type AllocateFn = unsafe extern "C" fn(size: usize, state: *mut c_void) -> *mut c_void;
+pub type DeallocateFn = unsafe extern "C" fn(pointer: *mut c_void, state: *mut c_void);
+pub type ReallocateFn =
+    unsafe extern "C" fn(pointer: *mut c_void, size: usize, state: *mut c_void) -> *mut c_void;
+pub type ZeroAllocateFn = unsafe extern "C" fn(
+    number_of_elements: usize,
+    size_of_element: usize,
+    state: *mut c_void,
+) -> *mut c_void;
+
+/// Custom allocator for the memory `rcl` manages on behalf of a context and its nodes
+///
+/// Once set with [`ContextBuilder::allocator`](crate::ContextBuilder::allocator), it is used
+/// to initialize the context, its nodes, their publishers and subscriptions, and the wait
+/// sets created by [`spin_once`](crate::spin_once). This makes pool or arena allocation
+/// strategies possible for deterministic memory use.
+///
+/// Messages are not affected: the generated message bindings convert through native messages
+/// allocated by their own C code, and Rust-side message fields use the global allocator.
+#[derive(Clone, Copy)]
+pub struct Allocator {
+    allocate: AllocateFn,
+    deallocate: DeallocateFn,
+    reallocate: ReallocateFn,
+    zero_allocate: ZeroAllocateFn,
+    state: *mut c_void,
+}
+
+impl Allocator {
+    /// Creates an allocator from the functions `rcl` calls, which all receive `state`
+    ///
+    /// # Safety
+    /// The functions must behave like `malloc`, `free`, `realloc` and `calloc` respectively,
+    /// and `state` must stay valid for as long as any entity created with this allocator.
+    pub unsafe fn from_raw_parts(
+        allocate: AllocateFn,
+        deallocate: DeallocateFn,
+        reallocate: ReallocateFn,
+        zero_allocate: ZeroAllocateFn,
+        state: *mut c_void,
+    ) -> Self {
+        Self {
+            allocate,
+            deallocate,
+            reallocate,
+            zero_allocate,
+            state,
+        }
+    }
+
+    pub(crate) fn to_rcl(self) -> rcutils_allocator_t {
+        rcutils_allocator_t {
+            allocate: Some(self.allocate),
+            deallocate: Some(self.deallocate),
+            reallocate: Some(self.reallocate),
+            zero_allocate: Some(self.zero_allocate),
+            state: self.state,
+        }
+    }
+}
+
+/// Returns the `rcl` form of `allocator`, falling back to the rcutils default allocator
+pub(crate) fn rcl_allocator(allocator: Option<&Allocator>) -> rcutils_allocator_t {
+    match allocator {
+        Some(allocator) => allocator.to_rcl(),
+        None => unsafe { rcutils_get_default_allocator() },
+    }
+}
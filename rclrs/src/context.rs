@@ -1,3 +1,4 @@
+use crate::allocator::{rcl_allocator, Allocator};
 use crate::error::{RclReturnCode, ToResult};
 use crate::rcl_bindings::*;
 use crate::Node;
@@ -28,15 +29,85 @@ impl ContextHandle {
 }
 
 impl Drop for ContextHandle {
+    /// Shuts down and finalizes the context
+    ///
+    /// Every [`NodeHandle`](crate::NodeHandle) keeps its context handle alive, so by the time
+    /// this runs no node (and therefore no publisher or subscription) can still be using it.
     fn drop(&mut self) {
+        let handle = &mut *self.get_mut();
         unsafe {
-            rcl_shutdown(&mut *self.get_mut() as *mut _);
+            rcl_shutdown(handle as *mut _);
+            rcl_context_fini(handle as *mut _);
         }
     }
 }
 
 pub struct Context {
     pub handle: Arc<ContextHandle>,
+    pub(crate) domain_id: Option<usize>,
+    pub(crate) use_global_arguments: bool,
+    pub(crate) allocator: Option<Allocator>,
+}
+
+/// Builder for a [`Context`] with non-default settings
+///
+/// Each built context is fully independent: dropping it shuts down and finalizes its `rcl`
+/// context once the last node created from it is gone.
+pub struct ContextBuilder {
+    args: Vec<CString>,
+    domain_id: Option<usize>,
+    use_global_arguments: bool,
+    allocator: Option<Allocator>,
+}
+
+impl ContextBuilder {
+    pub fn new(args: Vec<CString>) -> Self {
+        Self {
+            args,
+            domain_id: None,
+            use_global_arguments: true,
+            allocator: None,
+        }
+    }
+
+    /// Sets the ROS domain used by nodes created from this context
+    ///
+    /// When unset, nodes fall back to the `ROS_DOMAIN_ID` environment variable.
+    pub fn domain_id(mut self, domain_id: usize) -> Self {
+        self.domain_id = Some(domain_id);
+        self
+    }
+
+    /// Sets whether nodes created from this context follow the process-wide `--ros-args`
+    ///
+    /// Disable this for libraries embedded in larger applications, so that remappings meant
+    /// for the host application don't leak into their nodes. Individual nodes can override
+    /// it through [`NodeOptions::use_global_arguments`](crate::NodeOptions::use_global_arguments).
+    pub fn use_global_arguments(mut self, use_global_arguments: bool) -> Self {
+        self.use_global_arguments = use_global_arguments;
+        self
+    }
+
+    /// Sets the allocator used by `rcl` for this context and everything created from it
+    ///
+    /// When unset, the rcutils default allocator is used.
+    pub fn allocator(mut self, allocator: Allocator) -> Self {
+        self.allocator = Some(allocator);
+        self
+    }
+
+    pub fn build(self) -> Result<Context, RclReturnCode> {
+        let context = Context {
+            handle: Arc::new(ContextHandle(Mutex::new(unsafe {
+                rcl_get_zero_initialized_context()
+            }))),
+            domain_id: self.domain_id,
+            use_global_arguments: self.use_global_arguments,
+            allocator: self.allocator,
+        };
+        context.init(self.args)?;
+        Ok(context)
+    }
 }
 
 impl Context {
@@ -45,30 +116,48 @@ impl Context {
         let handle = &mut *self.handle.lock();
 
         unsafe {
-            let allocator = rcutils_get_default_allocator();
+            let allocator = rcl_allocator(self.allocator.as_ref());
             let mut init_options = rcl_get_zero_initialized_init_options();
-            rcl_init_options_init(&mut init_options as *mut _, allocator);
-            rcl_init(
-                c_args.len() as i32,
-                c_args.as_ptr(),
-                &init_options as *const _,
-                handle as *mut _,
-            )
-            .ok()?;
+            rcl_init_options_init(&mut init_options as *mut _, allocator).ok()?;
+            let init_result = self.set_domain_id(&mut init_options).and_then(|()| {
+                rcl_init(
+                    c_args.len() as i32,
+                    c_args.as_ptr(),
+                    &init_options as *const _,
+                    handle as *mut _,
+                )
+                .ok()
+            });
             rcl_init_options_fini(&mut init_options as *mut _).ok()?;
+            init_result?;
         }
 
         Ok(())
     }
 
+    // Foxy sets the domain id per node instead, see `Node::new_with_options`
+    #[cfg(ros_distro = "foxy")]
+    fn set_domain_id(&self, _init_options: &mut rcl_init_options_t) -> Result<(), RclReturnCode> {
+        Ok(())
+    }
+
+    #[cfg(not(ros_distro = "foxy"))]
+    fn set_domain_id(&self, init_options: &mut rcl_init_options_t) -> Result<(), RclReturnCode> {
+        match self.domain_id {
+            Some(domain_id) => unsafe {
+                rcl_init_options_set_domain_id(init_options as *mut _, domain_id).ok()
+            },
+            None => Ok(()),
+        }
+    }
+
+    pub fn builder(args: Vec<CString>) -> ContextBuilder {
+        ContextBuilder::new(args)
+    }
+
     pub fn default(args: Vec<CString>) -> Self {
-        let context = Self {
-            handle: Arc::new(ContextHandle(Mutex::new(unsafe {
-                rcl_get_zero_initialized_context()
-            }))),
-        };
-        context.init(args).unwrap(); // If we can't initialize the context, ROS 2 cannot function
-        context
+        // If we can't initialize the context, ROS 2 cannot function
+        Self::builder(args).build().unwrap()
     }
 
     pub fn ok(&self) -> Result<bool, RclReturnCode> {
@@ -13,22 +13,35 @@ extern crate parking_lot;
 #[cfg(not(feature = "std"))]
 extern crate spin;
 
+pub mod allocator;
 pub mod context;
 pub mod error;
+pub mod log;
 pub mod node;
 pub mod qos;
+pub mod serialization;
+pub mod type_support;
 pub mod wait;
 
 mod rcl_bindings;
 
+pub use self::allocator::*;
 pub use self::context::*;
 pub use self::error::*;
+pub use self::log::{set_logger_level, LogSeverity, ToLogParams};
 pub use self::node::*;
 pub use self::qos::*;
+pub use self::serialization::*;
+pub use self::type_support::*;
 
 use self::rcl_bindings::*;
+use alloc::rc;
+use alloc::sync::Weak;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
 use core::ops::{Deref, DerefMut};
-use wait::{WaitSet, WaitSetErrorResponse};
+use core::time::Duration;
+use wait::{WaitSet, WaitSetDescription, WaitSetErrorResponse};
 
 pub trait Handle<T> {
     type DerefT: Deref<Target = T>;
@@ -38,15 +51,132 @@ pub trait Handle<T> {
     fn get_mut(self) -> Self::DerefMutT;
 }
 
+/// What [`spin_with_options`] does when waiting fails with something other than a timeout
+pub enum WaitErrorPolicy {
+    /// Pause for `backoff` and try again, giving up after `max_retries` consecutive errors
+    ///
+    /// Only transient errors are retried. Errors that waiting again cannot fix, like an invalid
+    /// or empty wait set, still end the spin immediately.
+    Retry {
+        max_retries: usize,
+        backoff: Duration,
+    },
+    /// End the spin on the first error
+    Fail,
+}
+
+pub struct SpinOptions {
+    /// How long every iteration waits for work, see [`spin_once`]
+    ///
+    /// This also bounds how long a spin takes to notice that its context was shut down.
+    pub timeout: Duration,
+    pub on_wait_error: WaitErrorPolicy,
+}
+
+impl Default for SpinOptions {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_millis(500),
+            on_wait_error: WaitErrorPolicy::Retry {
+                max_retries: 10,
+                backoff: Duration::from_millis(10),
+            },
+        }
+    }
+}
+
+/// Counts consecutive wait errors and decides, per [`WaitErrorPolicy`], whether to keep spinning
+struct WaitErrorTracker<'policy> {
+    policy: &'policy WaitErrorPolicy,
+    consecutive_errors: usize,
+}
+
+impl<'policy> WaitErrorTracker<'policy> {
+    fn new(policy: &'policy WaitErrorPolicy) -> Self {
+        Self {
+            policy,
+            consecutive_errors: 0,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.consecutive_errors = 0;
+    }
+
+    /// Returns how long to pause before retrying, or hands the error back if spinning should end
+    fn on_error(&mut self, error: WaitSetErrorResponse) -> Result<Duration, WaitSetErrorResponse> {
+        match self.policy {
+            WaitErrorPolicy::Retry {
+                max_retries,
+                backoff,
+            } if is_transient(&error) && self.consecutive_errors < *max_retries => {
+                self.consecutive_errors += 1;
+                Ok(*backoff)
+            }
+            _ => Err(error),
+        }
+    }
+}
+
+/// Whether waiting again could plausibly succeed after `error`
+fn is_transient(error: &WaitSetErrorResponse) -> bool {
+    !matches!(
+        error,
+        WaitSetErrorResponse::ReturnCode(
+            RclReturnCode::InvalidArgument
+                | RclReturnCode::Unsupported
+                | RclReturnCode::RclError(_)
+                | RclReturnCode::NodeError(_)
+                | RclReturnCode::WaitSetError(_)
+        )
+    )
+}
+
 /// Wrapper around [`spin_once`]
+///
+/// Equivalent to [`spin_with_options`] with the default [`SpinOptions`].
 pub fn spin<'node>(node: &'node node::Node) -> Result<(), WaitSetErrorResponse> {
-    while unsafe { rcl_context_is_valid(&mut *node.context.lock() as *mut _) } {
-        if let Some(error) = spin_once(node, 500).err() {
-            match error {
-                WaitSetErrorResponse::DroppedSubscription
-                | WaitSetErrorResponse::ReturnCode(RclReturnCode::Timeout) => continue,
-                error => return Err(error),
-            };
+    spin_with_options(node, SpinOptions::default())
+}
+
+/// Calls [`spin_once`] until the node's context is shut down
+///
+/// Wait errors are handled according to `options.on_wait_error`. If the context was shut down
+/// while waiting, the spin ends successfully instead of reporting the resulting error.
+pub fn spin_with_options(node: &Node, options: SpinOptions) -> Result<(), WaitSetErrorResponse> {
+    // `rcl_wait` takes nanoseconds, and a negative timeout would block forever
+    let timeout = i64::try_from(options.timeout.as_nanos()).unwrap_or(i64::MAX);
+    spin_until_shutdown(
+        || spin_once(node, timeout),
+        || unsafe { rcl_context_is_valid(&mut *node.context.lock() as *mut _) },
+        &options.on_wait_error,
+    )
+}
+
+/// The loop of [`spin_with_options`], over any way of spinning once and of checking the context
+fn spin_until_shutdown(
+    mut spin_once: impl FnMut() -> Result<(), WaitSetErrorResponse>,
+    mut context_is_valid: impl FnMut() -> bool,
+    on_wait_error: &WaitErrorPolicy,
+) -> Result<(), WaitSetErrorResponse> {
+    let mut wait_errors = WaitErrorTracker::new(on_wait_error);
+
+    while context_is_valid() {
+        match spin_once() {
+            Ok(())
+            | Err(WaitSetErrorResponse::DroppedSubscription)
+            | Err(WaitSetErrorResponse::ReturnCode(RclReturnCode::Timeout)) => wait_errors.reset(),
+            Err(error) => {
+                if !context_is_valid() {
+                    break;
+                }
+                let backoff = wait_errors.on_error(error)?;
+                #[cfg(feature = "std")]
+                std::thread::sleep(backoff);
+                // Without std there is no portable way to sleep, so retry right away
+                #[cfg(not(feature = "std"))]
+                let _ = backoff;
+            }
         }
     }
 
@@ -91,16 +221,53 @@ pub fn spin<'node>(node: &'node node::Node) -> Result<(), WaitSetErrorResponse>
 ///
 ///
 pub fn spin_once<'node>(node: &'node Node, timeout: i64) -> Result<(), WaitSetErrorResponse> {
-    let number_of_subscriptions = node.subscriptions.len();
+    spin_once_then(node, timeout, |_, wait_result| {
+        wait_result.map_err(Into::into)
+    })
+}
+
+/// Like [`spin_once`], but describes the wait set it waited on, e.g. to find out why a callback
+/// never runs
+///
+/// Timing out is not an error here, the description then lists every entity as not ready.
+pub fn spin_once_described(
+    node: &Node,
+    timeout: i64,
+) -> Result<WaitSetDescription, WaitSetErrorResponse> {
+    spin_once_then(node, timeout, |wait_set, wait_result| match wait_result {
+        Ok(()) | Err(RclReturnCode::Timeout) => Ok(wait_set.describe()),
+        Err(error) => Err(error.into()),
+    })
+}
+
+/// Waits on the node's entities and executes the ready ones, then passes the wait set and the
+/// result of waiting to `finish`
+fn spin_once_then<R>(
+    node: &Node,
+    timeout: i64,
+    finish: impl FnOnce(&WaitSet, Result<(), RclReturnCode>) -> Result<R, WaitSetErrorResponse>,
+) -> Result<R, WaitSetErrorResponse> {
+    // Dropped entities are forgotten first, so they don't take up slots in the wait set
+    let subscriptions = {
+        let subscriptions = &mut *node.subscriptions.borrow_mut();
+        prune_dropped(subscriptions, Weak::strong_count);
+        subscriptions.clone()
+    };
+    let events: Vec<_> = {
+        let events = &mut *node.events.borrow_mut();
+        prune_dropped(events, rc::Weak::strong_count);
+        events.iter().filter_map(rc::Weak::upgrade).collect()
+    };
+    let number_of_subscriptions = subscriptions.len();
     let number_of_guard_conditions = 0;
     let number_of_timers = 0;
     let number_of_clients = 0;
     let number_of_services = 0;
-    let number_of_events = 0;
+    let number_of_events = events.len();
 
     let context = &mut *node.context.lock();
 
-    let mut wait_set = WaitSet::new(
+    let mut wait_set = WaitSet::new_with_allocator(
         number_of_subscriptions,
         number_of_guard_conditions,
         number_of_timers,
@@ -108,26 +275,205 @@ pub fn spin_once<'node>(node: &'node Node, timeout: i64) -> Result<(), WaitSetEr
         number_of_services,
         number_of_events,
         context,
+        node.allocator.as_ref(),
     )?;
 
-    for subscription in &node.subscriptions {
+    // Subscriptions take up consecutive slots, in the order they are added
+    let mut added_subscriptions = Vec::with_capacity(subscriptions.len());
+    for subscription in &subscriptions {
         match wait_set.add_subscription(subscription) {
-            Ok(()) => (),
+            Ok(()) => added_subscriptions.push(subscription),
             Err(WaitSetErrorResponse::DroppedSubscription) => (),
             Err(err) => return Err(err),
         };
     }
 
-    wait_set.wait(timeout)?;
-    for subscription in &node.subscriptions {
+    for event in &events {
+        wait_set.add_event(event)?;
+    }
+
+    let wait_result = wait_set.wait(timeout);
+    if wait_result.is_err() {
+        return finish(&wait_set, wait_result);
+    }
+    for (index, subscription) in added_subscriptions.into_iter().enumerate() {
+        if !wait_set.subscription_is_ready(index) {
+            continue;
+        }
         if let Some(subscription) = subscription.upgrade() {
-            let mut message = subscription.create_message();
-            let result = subscription.take(&mut *message).unwrap();
-            if result {
-                subscription.callback_fn(message);
+            match subscription.execute() {
+                Ok(true) => (),
+                Ok(false) => crate::log_debug!(node, "Spurious wakeup, no message was available"),
+                Err(error) => crate::log_error!(node, "Failed to take a message: {}", error),
             }
         }
     }
+    // Some rmw implementations report an event as taken even when its status did not change
+    for (index, event) in events.iter().enumerate() {
+        if !wait_set.event_is_ready(index) {
+            continue;
+        }
+        if let Err(error) = event.execute() {
+            crate::log_error!(node, "Failed to take a QoS event: {}", error);
+        }
+    }
 
-    Ok(())
+    finish(&wait_set, wait_result)
+}
+
+/// Removes the entities that were dropped by the user from a node's list
+fn prune_dropped<W>(entities: &mut Vec<W>, strong_count: fn(&W) -> usize) {
+    entities.retain(|entity| strong_count(entity) > 0);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{prune_dropped, spin_until_shutdown, WaitErrorPolicy, WaitErrorTracker};
+    use crate::error::{RclReturnCode, WaitSetErrorCode};
+    use crate::wait::WaitSetErrorResponse;
+    use alloc::sync::{Arc, Weak};
+    use alloc::vec::Vec;
+    use core::cell::Cell;
+    use core::time::Duration;
+
+    const RETRY: WaitErrorPolicy = WaitErrorPolicy::Retry {
+        max_retries: 2,
+        backoff: Duration::from_millis(5),
+    };
+
+    fn transient_error() -> WaitSetErrorResponse {
+        WaitSetErrorResponse::ReturnCode(RclReturnCode::Error)
+    }
+
+    fn fatal_error() -> WaitSetErrorResponse {
+        WaitSetErrorResponse::ReturnCode(RclReturnCode::WaitSetError(
+            WaitSetErrorCode::WaitSetInvalid,
+        ))
+    }
+
+    /// Spins through `results`, with the context shut down once they are used up
+    fn spin_through(
+        results: Vec<Result<(), WaitSetErrorResponse>>,
+        policy: &WaitErrorPolicy,
+    ) -> (Result<(), WaitSetErrorResponse>, usize) {
+        let mut results = results.into_iter();
+        let remaining = Cell::new(results.len());
+        let spins = Cell::new(0);
+        let result = spin_until_shutdown(
+            || {
+                spins.set(spins.get() + 1);
+                remaining.set(remaining.get() - 1);
+                results.next().unwrap()
+            },
+            || remaining.get() > 0,
+            policy,
+        );
+        (result, spins.get())
+    }
+
+    #[test]
+    fn test_spin_recovers_from_transient_errors() {
+        let results = alloc::vec![Err(transient_error()), Err(transient_error()), Ok(())];
+        let (result, spins) = spin_through(results, &RETRY);
+        assert!(result.is_ok());
+        assert_eq!(spins, 3);
+    }
+
+    #[test]
+    fn test_spin_resets_retries_after_success() {
+        // Four errors in total, but never more than `max_retries` in a row
+        let results = alloc::vec![
+            Err(transient_error()),
+            Err(transient_error()),
+            Ok(()),
+            Err(transient_error()),
+            Err(transient_error()),
+            Ok(()),
+        ];
+        let (result, spins) = spin_through(results, &RETRY);
+        assert!(result.is_ok());
+        assert_eq!(spins, 6);
+    }
+
+    #[test]
+    fn test_spin_gives_up_after_max_retries() {
+        let results = alloc::vec![
+            Err(transient_error()),
+            Err(transient_error()),
+            Err(transient_error()),
+            Ok(()),
+        ];
+        let (result, spins) = spin_through(results, &RETRY);
+        assert!(result.is_err());
+        assert_eq!(spins, 3);
+    }
+
+    #[test]
+    fn test_spin_ends_quietly_after_shutdown() {
+        // The error is caused by the shutdown, so it is not reported
+        let (result, spins) = spin_through(alloc::vec![Err(fatal_error())], &WaitErrorPolicy::Fail);
+        assert!(result.is_ok());
+        assert_eq!(spins, 1);
+    }
+
+    #[test]
+    fn test_spin_reports_fatal_errors() {
+        let results = alloc::vec![Err(fatal_error()), Ok(())];
+        let (result, spins) = spin_through(results, &RETRY);
+        assert!(result.is_err());
+        assert_eq!(spins, 1);
+    }
+
+    #[test]
+    fn test_transient_error_is_retried() {
+        let mut tracker = WaitErrorTracker::new(&RETRY);
+        assert_eq!(
+            tracker.on_error(transient_error()).unwrap(),
+            Duration::from_millis(5)
+        );
+    }
+
+    #[test]
+    fn test_retries_are_capped() {
+        let mut tracker = WaitErrorTracker::new(&RETRY);
+        assert!(tracker.on_error(transient_error()).is_ok());
+        assert!(tracker.on_error(transient_error()).is_ok());
+        assert!(tracker.on_error(transient_error()).is_err());
+    }
+
+    #[test]
+    fn test_recovery_resets_retries() {
+        let mut tracker = WaitErrorTracker::new(&RETRY);
+        assert!(tracker.on_error(transient_error()).is_ok());
+        assert!(tracker.on_error(transient_error()).is_ok());
+        tracker.reset();
+        assert!(tracker.on_error(transient_error()).is_ok());
+    }
+
+    #[test]
+    fn test_fatal_error_is_not_retried() {
+        let mut tracker = WaitErrorTracker::new(&RETRY);
+        assert!(tracker.on_error(fatal_error()).is_err());
+    }
+
+    #[test]
+    fn test_prune_dropped() {
+        let kept = Arc::new(1);
+        let mut entities: Vec<Weak<i32>> = Vec::new();
+        for _ in 0..3 {
+            let dropped = Arc::new(0);
+            entities.push(Arc::downgrade(&dropped));
+        }
+        entities.push(Arc::downgrade(&kept));
+        prune_dropped(&mut entities, Weak::strong_count);
+
+        assert_eq!(entities.len(), 1);
+        assert_eq!(entities[0].upgrade().as_deref(), Some(&1));
+    }
+
+    #[test]
+    fn test_fail_policy() {
+        let mut tracker = WaitErrorTracker::new(&WaitErrorPolicy::Fail);
+        assert!(tracker.on_error(transient_error()).is_err());
+    }
 }
@@ -0,0 +1,166 @@
+use crate::error::{RclReturnCode, ToResult};
+use crate::rcl_bindings::*;
+use crate::Node;
+use alloc::fmt;
+use alloc::string::String;
+use cstr_core::{c_char, CString};
+
+/// Severity of a log message, with the same values as rcutils' `RCUTILS_LOG_SEVERITY`
+#[repr(i32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogSeverity {
+    /// Inherit the level of the parent logger, only meaningful for [`set_logger_level`]
+    Unset = 0,
+    Debug = 10,
+    Info = 20,
+    Warn = 30,
+    Error = 40,
+    Fatal = 50,
+}
+
+/// Anything that names the logger a message is sent to
+///
+/// A [`Node`] logs under its `rcl` logger name, which is derived from its namespace and name.
+pub trait ToLogParams {
+    fn logger_name(&self) -> String;
+}
+
+impl ToLogParams for str {
+    fn logger_name(&self) -> String {
+        String::from(self)
+    }
+}
+
+impl ToLogParams for String {
+    fn logger_name(&self) -> String {
+        self.clone()
+    }
+}
+
+impl ToLogParams for Node {
+    fn logger_name(&self) -> String {
+        String::from(Node::logger_name(self))
+    }
+}
+
+impl<L: ToLogParams + ?Sized> ToLogParams for &L {
+    fn logger_name(&self) -> String {
+        (**self).logger_name()
+    }
+}
+
+/// Sets the minimum severity a logger, and the loggers below it, will output
+///
+/// Messages are filtered before they are formatted, so disabled levels cost next to nothing.
+pub fn set_logger_level(logger_name: &str, severity: LogSeverity) -> Result<(), RclReturnCode> {
+    let logger_name = CString::new(logger_name).map_err(|_| RclReturnCode::InvalidArgument)?;
+    unsafe { rcutils_logging_set_logger_level(logger_name.as_ptr(), severity as i32).ok() }
+}
+
+/// Implementation of the `log_*!` macros, use those instead
+#[doc(hidden)]
+pub fn log(
+    logger: &dyn ToLogParams,
+    severity: LogSeverity,
+    module_path: &'static str,
+    file: &'static str,
+    line: u32,
+    args: fmt::Arguments,
+) {
+    let logger_name = match CString::new(logger.logger_name()) {
+        Ok(logger_name) => logger_name,
+        Err(_) => return,
+    };
+
+    unsafe {
+        if !rcutils_logging_logger_is_enabled_for(logger_name.as_ptr(), severity as i32) {
+            return;
+        }
+
+        let message = to_c_message(args);
+        let location = rcutils_log_location_t {
+            function_name: module_path.as_ptr() as *const c_char,
+            file_name: file.as_ptr() as *const c_char,
+            line_number: line as usize,
+        };
+        // The message is passed as an argument, so `%` in it is not read as a format specifier
+        rcutils_log(
+            &location as *const _,
+            severity as i32,
+            logger_name.as_ptr(),
+            "%s\0".as_ptr() as *const c_char,
+            message.as_ptr(),
+        );
+    }
+}
+
+fn to_c_message(args: fmt::Arguments) -> CString {
+    let message = fmt::format(args);
+    // Interior nul bytes would cut the message short, so they are escaped instead
+    CString::new(message.replace('\0', "\\0")).unwrap()
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __log {
+    ($severity:ident, $logger:expr, $($arg:tt)+) => {
+        $crate::log::log(
+            &$logger,
+            $crate::log::LogSeverity::$severity,
+            concat!(module_path!(), "\0"),
+            concat!(file!(), "\0"),
+            line!(),
+            format_args!($($arg)+),
+        )
+    };
+}
+
+/// Logs a message at [`LogSeverity::Debug`], e.g. `log_debug!(node, "{} bytes left", n)`
+///
+/// The first argument is anything implementing [`ToLogParams`], the rest is passed to
+/// `format_args!`.
+#[macro_export]
+macro_rules! log_debug {
+    ($logger:expr, $($arg:tt)+) => { $crate::__log!(Debug, $logger, $($arg)+) };
+}
+
+/// Logs a message at [`LogSeverity::Info`], see [`log_debug!`]
+#[macro_export]
+macro_rules! log_info {
+    ($logger:expr, $($arg:tt)+) => { $crate::__log!(Info, $logger, $($arg)+) };
+}
+
+/// Logs a message at [`LogSeverity::Warn`], see [`log_debug!`]
+#[macro_export]
+macro_rules! log_warn {
+    ($logger:expr, $($arg:tt)+) => { $crate::__log!(Warn, $logger, $($arg)+) };
+}
+
+/// Logs a message at [`LogSeverity::Error`], see [`log_debug!`]
+#[macro_export]
+macro_rules! log_error {
+    ($logger:expr, $($arg:tt)+) => { $crate::__log!(Error, $logger, $($arg)+) };
+}
+
+/// Logs a message at [`LogSeverity::Fatal`], see [`log_debug!`]
+#[macro_export]
+macro_rules! log_fatal {
+    ($logger:expr, $($arg:tt)+) => { $crate::__log!(Fatal, $logger, $($arg)+) };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::to_c_message;
+
+    #[test]
+    fn test_message_is_formatted() {
+        let message = to_c_message(format_args!("{} of {}", 1, 2));
+        assert_eq!(message.to_bytes(), b"1 of 2");
+    }
+
+    #[test]
+    fn test_nul_bytes_are_escaped() {
+        let message = to_c_message(format_args!("a{}b", '\0'));
+        assert_eq!(message.to_bytes(), b"a\\0b");
+    }
+}
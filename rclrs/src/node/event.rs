@@ -0,0 +1,378 @@
+use crate::error::{to_rcl_result, EventErrorCode, RclReturnCode, ToResult};
+use crate::rcl_bindings::*;
+use crate::{PublisherHandle, SubscriptionHandle};
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use core::cell::RefCell;
+use core::mem::MaybeUninit;
+
+#[cfg(not(feature = "std"))]
+use spin::{Mutex, MutexGuard};
+
+#[cfg(feature = "std")]
+use parking_lot::{Mutex, MutexGuard};
+
+/// The entity a QoS event belongs to, which must outlive the event
+// The handles are never read, they are only kept alive
+#[allow(dead_code)]
+enum EventParent {
+    Publisher(Arc<PublisherHandle>),
+    Subscription(Arc<SubscriptionHandle>),
+}
+
+pub struct EventHandle {
+    handle: Mutex<rcl_event_t>,
+    _parent: EventParent,
+}
+
+impl EventHandle {
+    pub fn get_mut(&mut self) -> &mut rcl_event_t {
+        self.handle.get_mut()
+    }
+
+    pub fn lock(&self) -> MutexGuard<rcl_event_t> {
+        self.handle.lock()
+    }
+
+    pub fn try_lock(&self) -> Option<MutexGuard<rcl_event_t>> {
+        self.handle.try_lock()
+    }
+}
+
+impl Drop for EventHandle {
+    fn drop(&mut self) {
+        let handle = self.handle.get_mut();
+        unsafe {
+            rcl_event_fini(handle as *mut _);
+        }
+    }
+}
+
+/// Status of a missed deadline, either requested by a subscription or offered by a publisher
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DeadlineMissedStatus {
+    /// Number of deadlines missed since the entity was created
+    pub total_count: i32,
+    /// Number of deadlines missed since the last time the event was taken
+    pub total_count_change: i32,
+}
+
+impl From<rmw_requested_deadline_missed_status_t> for DeadlineMissedStatus {
+    fn from(status: rmw_requested_deadline_missed_status_t) -> Self {
+        Self {
+            total_count: status.total_count,
+            total_count_change: status.total_count_change,
+        }
+    }
+}
+
+impl From<rmw_offered_deadline_missed_status_t> for DeadlineMissedStatus {
+    fn from(status: rmw_offered_deadline_missed_status_t) -> Self {
+        Self {
+            total_count: status.total_count,
+            total_count_change: status.total_count_change,
+        }
+    }
+}
+
+/// Change in the liveliness of the publishers a subscription is matched with
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LivelinessChangedStatus {
+    /// Number of matched publishers that are currently alive
+    pub alive_count: i32,
+    /// Number of matched publishers that are currently not alive
+    pub not_alive_count: i32,
+    pub alive_count_change: i32,
+    pub not_alive_count_change: i32,
+}
+
+impl From<rmw_liveliness_changed_status_t> for LivelinessChangedStatus {
+    fn from(status: rmw_liveliness_changed_status_t) -> Self {
+        Self {
+            alive_count: status.alive_count,
+            not_alive_count: status.not_alive_count,
+            alive_count_change: status.alive_count_change,
+            not_alive_count_change: status.not_alive_count_change,
+        }
+    }
+}
+
+/// Status of a publisher that failed to assert its liveliness within its lease duration
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LivelinessLostStatus {
+    pub total_count: i32,
+    pub total_count_change: i32,
+}
+
+impl From<rmw_liveliness_lost_status_t> for LivelinessLostStatus {
+    fn from(status: rmw_liveliness_lost_status_t) -> Self {
+        Self {
+            total_count: status.total_count,
+            total_count_change: status.total_count_change,
+        }
+    }
+}
+
+/// The QoS policy that made a publisher and a subscription incompatible
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum QoSPolicyKind {
+    Invalid,
+    Durability,
+    Deadline,
+    Liveliness,
+    Reliability,
+    History,
+    Lifespan,
+    /// Only reported since Galactic
+    Depth,
+    /// Only reported since Galactic
+    LivelinessLeaseDuration,
+    /// Only reported since Galactic
+    AvoidRosNamespaceConventions,
+}
+
+impl QoSPolicyKind {
+    // The values of `rmw_qos_policy_kind_t`, which gains variants between distributions
+    fn from_raw(policy_kind: u32) -> Self {
+        match policy_kind {
+            2 => Self::Durability,
+            4 => Self::Deadline,
+            8 => Self::Liveliness,
+            16 => Self::Reliability,
+            32 => Self::History,
+            64 => Self::Lifespan,
+            128 => Self::Depth,
+            256 => Self::LivelinessLeaseDuration,
+            512 => Self::AvoidRosNamespaceConventions,
+            _ => Self::Invalid,
+        }
+    }
+}
+
+impl From<rmw_qos_policy_kind_t> for QoSPolicyKind {
+    fn from(policy_kind: rmw_qos_policy_kind_t) -> Self {
+        Self::from_raw(policy_kind as u32)
+    }
+}
+
+/// Status of a publisher or subscription that was matched with a peer whose QoS is incompatible
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct IncompatibleQoSStatus {
+    /// Number of incompatible peers found since the entity was created
+    pub total_count: i32,
+    pub total_count_change: i32,
+    /// The policy that was incompatible for the most recently found peer
+    pub last_policy_kind: QoSPolicyKind,
+}
+
+impl From<rmw_qos_incompatible_event_status_t> for IncompatibleQoSStatus {
+    fn from(status: rmw_qos_incompatible_event_status_t) -> Self {
+        Self {
+            total_count: status.total_count,
+            total_count_change: status.total_count_change,
+            last_policy_kind: status.last_policy_kind.into(),
+        }
+    }
+}
+
+/// A QoS event of a subscription, together with the callback that handles it
+pub enum SubscriptionEventCallback {
+    RequestedDeadlineMissed(Box<dyn FnMut(DeadlineMissedStatus) + 'static>),
+    LivelinessChanged(Box<dyn FnMut(LivelinessChangedStatus) + 'static>),
+    RequestedIncompatibleQoS(Box<dyn FnMut(IncompatibleQoSStatus) + 'static>),
+}
+
+/// A QoS event of a publisher, together with the callback that handles it
+pub enum PublisherEventCallback {
+    OfferedDeadlineMissed(Box<dyn FnMut(DeadlineMissedStatus) + 'static>),
+    LivelinessLost(Box<dyn FnMut(LivelinessLostStatus) + 'static>),
+    OfferedIncompatibleQoS(Box<dyn FnMut(IncompatibleQoSStatus) + 'static>),
+}
+
+enum EventCallback {
+    Publisher(PublisherEventCallback),
+    Subscription(SubscriptionEventCallback),
+}
+
+/// QoS event of a publisher or subscription, whose callback is called while spinning
+///
+/// Create one with [`Node::create_subscription_event`](crate::Node::create_subscription_event)
+/// or [`Node::create_publisher_event`](crate::Node::create_publisher_event). The event stops
+/// being handled once it is dropped.
+pub struct QoSEvent {
+    // Like the node that owns it, an event is only used by the thread spinning the node
+    pub handle: EventHandle,
+    callback: RefCell<EventCallback>,
+}
+
+impl QoSEvent {
+    /// # Errors
+    /// - `RclReturnCode::Unsupported` if the rmw implementation does not support this event
+    pub(crate) fn new_for_subscription(
+        subscription: &Arc<SubscriptionHandle>,
+        callback: SubscriptionEventCallback,
+    ) -> Result<Self, RclReturnCode> {
+        let event_type = match callback {
+            SubscriptionEventCallback::RequestedDeadlineMissed(_) => {
+                rcl_subscription_event_type_t::RCL_SUBSCRIPTION_REQUESTED_DEADLINE_MISSED
+            }
+            SubscriptionEventCallback::LivelinessChanged(_) => {
+                rcl_subscription_event_type_t::RCL_SUBSCRIPTION_LIVELINESS_CHANGED
+            }
+            SubscriptionEventCallback::RequestedIncompatibleQoS(_) => {
+                rcl_subscription_event_type_t::RCL_SUBSCRIPTION_REQUESTED_INCOMPATIBLE_QOS
+            }
+        };
+        let mut event_handle = unsafe { rcl_get_zero_initialized_event() };
+        let subscription_handle = &*subscription.lock();
+        unsafe {
+            rcl_subscription_event_init(
+                &mut event_handle as *mut _,
+                subscription_handle as *const _,
+                event_type,
+            )
+            .ok()?;
+        }
+
+        Ok(Self {
+            handle: EventHandle {
+                handle: Mutex::new(event_handle),
+                _parent: EventParent::Subscription(subscription.clone()),
+            },
+            callback: RefCell::new(EventCallback::Subscription(callback)),
+        })
+    }
+
+    /// # Errors
+    /// - `RclReturnCode::Unsupported` if the rmw implementation does not support this event
+    pub(crate) fn new_for_publisher(
+        publisher: &Arc<PublisherHandle>,
+        callback: PublisherEventCallback,
+    ) -> Result<Self, RclReturnCode> {
+        let event_type = match callback {
+            PublisherEventCallback::OfferedDeadlineMissed(_) => {
+                rcl_publisher_event_type_t::RCL_PUBLISHER_OFFERED_DEADLINE_MISSED
+            }
+            PublisherEventCallback::LivelinessLost(_) => {
+                rcl_publisher_event_type_t::RCL_PUBLISHER_LIVELINESS_LOST
+            }
+            PublisherEventCallback::OfferedIncompatibleQoS(_) => {
+                rcl_publisher_event_type_t::RCL_PUBLISHER_OFFERED_INCOMPATIBLE_QOS
+            }
+        };
+        let mut event_handle = unsafe { rcl_get_zero_initialized_event() };
+        let publisher_handle = &*publisher.lock();
+        unsafe {
+            rcl_publisher_event_init(
+                &mut event_handle as *mut _,
+                publisher_handle as *const _,
+                event_type,
+            )
+            .ok()?;
+        }
+
+        Ok(Self {
+            handle: EventHandle {
+                handle: Mutex::new(event_handle),
+                _parent: EventParent::Publisher(publisher.clone()),
+            },
+            callback: RefCell::new(EventCallback::Publisher(callback)),
+        })
+    }
+
+    /// Takes the event's status, if it changed, and passes it to the callback
+    ///
+    /// Returns whether a status was taken.
+    pub fn execute(&self) -> Result<bool, RclReturnCode> {
+        let handle = &self.handle;
+        match &mut *self.callback.borrow_mut() {
+            EventCallback::Subscription(SubscriptionEventCallback::RequestedDeadlineMissed(
+                callback,
+            )) => take_event::<rmw_requested_deadline_missed_status_t, _>(handle, callback),
+            EventCallback::Subscription(SubscriptionEventCallback::LivelinessChanged(callback)) => {
+                take_event::<rmw_liveliness_changed_status_t, _>(handle, callback)
+            }
+            EventCallback::Subscription(SubscriptionEventCallback::RequestedIncompatibleQoS(
+                callback,
+            )) => take_event::<rmw_requested_qos_incompatible_event_status_t, _>(handle, callback),
+            EventCallback::Publisher(PublisherEventCallback::OfferedDeadlineMissed(callback)) => {
+                take_event::<rmw_offered_deadline_missed_status_t, _>(handle, callback)
+            }
+            EventCallback::Publisher(PublisherEventCallback::LivelinessLost(callback)) => {
+                take_event::<rmw_liveliness_lost_status_t, _>(handle, callback)
+            }
+            EventCallback::Publisher(PublisherEventCallback::OfferedIncompatibleQoS(callback)) => {
+                take_event::<rmw_offered_qos_incompatible_event_status_t, _>(handle, callback)
+            }
+        }
+    }
+}
+
+/// Takes an event's status as the rmw struct `R`, and passes it to `callback` as `S`
+///
+/// +-------------------+
+/// | rclrs::take_event |
+/// +---------+---------+
+///           |
+///           |
+/// +---------v---------+
+/// |  rcl_take_event   |
+/// +-------------------+
+fn take_event<R, S>(
+    handle: &EventHandle,
+    callback: &mut Box<dyn FnMut(S) + 'static>,
+) -> Result<bool, RclReturnCode>
+where
+    S: From<R>,
+{
+    let event_handle = &*handle.lock();
+    let mut status = MaybeUninit::<R>::uninit();
+    let result = unsafe { rcl_take_event(event_handle as *const _, status.as_mut_ptr() as *mut _) };
+
+    match to_rcl_result(result) {
+        Ok(()) => {
+            // rmw filled in the status, since the event was taken
+            callback(unsafe { status.assume_init() }.into());
+            Ok(true)
+        }
+        Err(RclReturnCode::EventError(EventErrorCode::EventTakeFailed)) => Ok(false),
+        Err(error) => Err(error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{IncompatibleQoSStatus, QoSPolicyKind};
+    use crate::rcl_bindings::*;
+
+    #[test]
+    fn test_incompatible_qos_status_conversion() {
+        let status: IncompatibleQoSStatus = rmw_qos_incompatible_event_status_t {
+            total_count: 3,
+            total_count_change: 1,
+            last_policy_kind: rmw_qos_policy_kind_t::RMW_QOS_POLICY_RELIABILITY,
+        }
+        .into();
+
+        assert_eq!(
+            status,
+            IncompatibleQoSStatus {
+                total_count: 3,
+                total_count_change: 1,
+                last_policy_kind: QoSPolicyKind::Reliability,
+            }
+        );
+    }
+
+    #[test]
+    fn test_policy_kind_from_raw() {
+        assert_eq!(QoSPolicyKind::from_raw(1), QoSPolicyKind::Invalid);
+        assert_eq!(QoSPolicyKind::from_raw(64), QoSPolicyKind::Lifespan);
+        assert_eq!(
+            QoSPolicyKind::from_raw(512),
+            QoSPolicyKind::AvoidRosNamespaceConventions
+        );
+        // Policies added by later distributions are not mistaken for known ones
+        assert_eq!(QoSPolicyKind::from_raw(1024), QoSPolicyKind::Invalid);
+    }
+}
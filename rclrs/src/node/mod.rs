@@ -1,16 +1,23 @@
 use alloc::{
+    format,
+    rc::{self, Rc},
+    string::{String, ToString},
     sync::{Arc, Weak},
     vec::Vec,
 };
+use core::cell::RefCell;
 
-use crate::error::{RclReturnCode, ToResult};
+use crate::allocator::{rcl_allocator, Allocator};
+use crate::error::{NodeErrorCode, RclReturnCode, ToResult};
 use crate::qos::QoSProfile;
 use crate::rcl_bindings::*;
 use rclrs_msg_utilities::traits::MessageDefinition;
 
 use crate::{Context, ContextHandle};
-use cstr_core::CString;
+use cstr_core::{c_char, CStr, CString};
 
+pub mod event;
+pub use self::event::*;
 pub mod publisher;
 pub use self::publisher::*;
 pub mod subscription;
@@ -22,19 +29,23 @@ use spin::{Mutex, MutexGuard};
 #[cfg(feature = "std")]
 use parking_lot::{Mutex, MutexGuard};
 
-pub struct NodeHandle(Mutex<rcl_node_t>);
+pub struct NodeHandle {
+    handle: Mutex<rcl_node_t>,
+    // The node must be finalized before its context, so it keeps the context alive
+    _context_handle: Arc<ContextHandle>,
+}
 
 impl NodeHandle {
     pub fn get_mut(&mut self) -> &mut rcl_node_t {
-        self.0.get_mut()
+        self.handle.get_mut()
     }
 
     pub fn lock(&self) -> MutexGuard<rcl_node_t> {
-        self.0.lock()
+        self.handle.lock()
     }
 
     pub fn try_lock(&self) -> Option<MutexGuard<rcl_node_t>> {
-        self.0.try_lock()
+        self.handle.try_lock()
     }
 }
 
@@ -45,10 +56,42 @@ impl Drop for NodeHandle {
     }
 }
 
+/// Options for creating a [`Node`] with [`Node::new_with_options`]
+#[derive(Default)]
+pub struct NodeOptions {
+    use_global_arguments: Option<bool>,
+    arguments: Vec<String>,
+}
+
+impl NodeOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets whether the node is affected by the `--ros-args` its context was created with
+    ///
+    /// When unset, the context's setting is used, see
+    /// [`ContextBuilder::use_global_arguments`](crate::ContextBuilder::use_global_arguments).
+    pub fn use_global_arguments(mut self, use_global_arguments: bool) -> Self {
+        self.use_global_arguments = Some(use_global_arguments);
+        self
+    }
+
+    /// Sets arguments that only apply to this node, e.g. `["--ros-args", "-r", "foo:=bar"]`
+    pub fn arguments(mut self, arguments: Vec<String>) -> Self {
+        self.arguments = arguments;
+        self
+    }
+}
+
 pub struct Node {
     handle: Arc<NodeHandle>,
     pub(crate) context: Arc<ContextHandle>,
-    pub(crate) subscriptions: Vec<Weak<dyn SubscriptionBase>>,
+    // Shared with every sub-node, so spinning any of them serves all their subscriptions
+    pub(crate) subscriptions: Rc<RefCell<Vec<Weak<dyn SubscriptionBase>>>>,
+    pub(crate) events: Rc<RefCell<Vec<rc::Weak<QoSEvent>>>>,
+    pub(crate) allocator: Option<Allocator>,
+    sub_namespace: String,
 }
 
 impl Node {
@@ -61,34 +104,157 @@ impl Node {
         node_name: &str,
         node_ns: &str,
         context: &Context,
+    ) -> Result<Node, RclReturnCode> {
+        Self::new_with_options(node_name, node_ns, context, NodeOptions::default())
+    }
+
+    pub fn new_with_options(
+        node_name: &str,
+        node_ns: &str,
+        context: &Context,
+        options: NodeOptions,
     ) -> Result<Node, RclReturnCode> {
         let raw_node_name = CString::new(node_name).unwrap();
         let raw_node_ns = CString::new(node_ns).unwrap();
+        let raw_arguments: Vec<CString> = options
+            .arguments
+            .iter()
+            .map(|argument| CString::new(argument.as_str()).unwrap())
+            .collect();
+        let c_arguments: Vec<*const c_char> =
+            raw_arguments.iter().map(|arg| arg.as_ptr()).collect();
 
         let mut node_handle = unsafe { rcl_get_zero_initialized_node() };
         let context_handle = &mut *context.handle.lock();
 
         unsafe {
-            let node_options = rcl_node_get_default_options();
-            rcl_node_init(
+            let mut node_options = rcl_node_get_default_options();
+            node_options.allocator = rcl_allocator(context.allocator.as_ref());
+            // Later distributions set the domain id on the context's init options instead
+            #[cfg(ros_distro = "foxy")]
+            if let Some(domain_id) = context.domain_id {
+                node_options.domain_id = domain_id;
+            }
+            node_options.use_global_arguments = options
+                .use_global_arguments
+                .unwrap_or(context.use_global_arguments);
+            if !c_arguments.is_empty() {
+                rcl_parse_arguments(
+                    c_arguments.len() as i32,
+                    c_arguments.as_ptr(),
+                    rcl_allocator(context.allocator.as_ref()),
+                    &mut node_options.arguments as *mut _,
+                )
+                .ok()?;
+            }
+            let init_result = rcl_node_init(
                 &mut node_handle as *mut _,
                 raw_node_name.as_ptr(),
                 raw_node_ns.as_ptr(),
                 context_handle as *mut _,
                 &node_options as *const _,
-            )
-            .ok()?;
+            );
+            // `rcl_node_init` keeps its own copy of the options
+            rcl_node_options_fini(&mut node_options as *mut _).ok()?;
+            init_result.ok()?;
         }
 
-        let handle = Arc::new(NodeHandle(Mutex::new(node_handle)));
+        let handle = Arc::new(NodeHandle {
+            handle: Mutex::new(node_handle),
+            _context_handle: context.handle.clone(),
+        });
+        // Nodes are not shared across threads, the lists only need to be shared with sub-nodes
+        let subscriptions = Rc::new(RefCell::new(alloc::vec![]));
+        let events = Rc::new(RefCell::new(alloc::vec![]));
 
         Ok(Node {
             handle,
             context: context.handle.clone(),
-            subscriptions: alloc::vec![],
+            subscriptions,
+            events,
+            allocator: context.allocator,
+            sub_namespace: String::new(),
+        })
+    }
+
+    /// Creates a sub-node, which shares this node's `rcl` node but prefixes the names of the
+    /// entities it creates with `sub_namespace`
+    ///
+    /// For example, a subscription to `"odom"` created through the sub-node `"wheels"` of a
+    /// node in namespace `/robot` subscribes to `/robot/wheels/odom`. Absolute (`/...`) and
+    /// private (`~...`) names are used as they are. Sub-nodes can be nested.
+    ///
+    /// # Errors
+    /// - `NodeErrorCode::NodeInvalidNamespace` if `sub_namespace` is empty or absolute
+    pub fn create_sub_node(&self, sub_namespace: &str) -> Result<Node, RclReturnCode> {
+        if sub_namespace.is_empty() || sub_namespace.starts_with('/') {
+            return Err(NodeErrorCode::NodeInvalidNamespace.into());
+        }
+
+        Ok(Node {
+            handle: self.handle.clone(),
+            context: self.context.clone(),
+            subscriptions: self.subscriptions.clone(),
+            events: self.events.clone(),
+            allocator: self.allocator,
+            sub_namespace: extend_name_with_sub_namespace(sub_namespace, &self.sub_namespace),
         })
     }
 
+    /// The sub-namespace added to relative names, empty unless this is a sub-node
+    pub fn sub_namespace(&self) -> &str {
+        &self.sub_namespace
+    }
+
+    /// Name of the logger this node logs to with the [`log_info!`](crate::log_info) family
+    pub fn logger_name(&self) -> &str {
+        let node_handle = &*self.handle.lock();
+        // The name is owned by the rcl node, which lives at least as long as `self`
+        unsafe {
+            let logger_name = rcl_node_get_logger_name(node_handle as *const _);
+            if logger_name.is_null() {
+                return "";
+            }
+            CStr::from_ptr(logger_name).to_str().unwrap_or("")
+        }
+    }
+
+    /// Fully qualified names of the nodes this node has discovered, including itself
+    pub fn get_node_names(&self) -> Result<Vec<String>, RclReturnCode> {
+        let node_handle = &*self.handle.lock();
+        let mut names = unsafe { rcutils_get_zero_initialized_string_array() };
+        let mut namespaces = unsafe { rcutils_get_zero_initialized_string_array() };
+
+        let result = unsafe {
+            rcl_get_node_names(
+                node_handle as *const _,
+                rcl_allocator(self.allocator.as_ref()),
+                &mut names as *mut _,
+                &mut namespaces as *mut _,
+            )
+            .ok()
+        };
+        let node_names = result.map(|()| unsafe {
+            (0..names.size)
+                .map(|index| {
+                    let name = CStr::from_ptr(*names.data.add(index)).to_string_lossy();
+                    let namespace = CStr::from_ptr(*namespaces.data.add(index)).to_string_lossy();
+                    fully_qualified_name(&namespace, &name)
+                })
+                .collect()
+        });
+        unsafe {
+            rcutils_string_array_fini(&mut names as *mut _);
+            rcutils_string_array_fini(&mut namespaces as *mut _);
+        }
+
+        node_names
+    }
+
+    pub(crate) fn extend_name(&self, name: &str) -> String {
+        extend_name_with_sub_namespace(name, &self.sub_namespace)
+    }
+
     // TODO: make publisher's lifetime depend on node's lifetime
     pub fn create_publisher<T>(
         &self,
@@ -103,7 +269,7 @@ impl Node {
 
     // TODO: make subscription's lifetime depend on node's lifetime
     pub fn create_subscription<T, F>(
-        &mut self,
+        &self,
         topic: &str,
         qos: QoSProfile,
         callback: F,
@@ -114,7 +280,142 @@ impl Node {
     {
         let subscription = Arc::new(Subscription::<T>::new(self, topic, qos, callback)?);
         self.subscriptions
+            .borrow_mut()
             .push(Arc::downgrade(&subscription) as Weak<dyn SubscriptionBase>);
         Ok(subscription)
     }
+
+    /// Creates a subscription whose callback also receives the [`MessageInfo`] of each message,
+    /// e.g. to measure latency or to tell publishers apart
+    pub fn create_subscription_with_info<T, F>(
+        &self,
+        topic: &str,
+        qos: QoSProfile,
+        callback: F,
+    ) -> Result<Arc<Subscription<T>>, RclReturnCode>
+    where
+        T: MessageDefinition<T> + Default,
+        F: FnMut(&T, MessageInfo) + Sized + 'static,
+    {
+        let subscription = Arc::new(Subscription::<T>::new_with_info(
+            self, topic, qos, callback,
+        )?);
+        self.subscriptions
+            .borrow_mut()
+            .push(Arc::downgrade(&subscription) as Weak<dyn SubscriptionBase>);
+        Ok(subscription)
+    }
+
+    /// Registers a callback for a QoS event of `subscription`, e.g. a requested deadline it missed
+    ///
+    /// The callback is called while spinning, like the subscription's own callback, for as long
+    /// as the returned event is kept alive.
+    ///
+    /// # Errors
+    /// - `RclReturnCode::Unsupported` if the rmw implementation does not support the event
+    pub fn create_subscription_event<T>(
+        &self,
+        subscription: &Subscription<T>,
+        callback: SubscriptionEventCallback,
+    ) -> Result<Rc<QoSEvent>, RclReturnCode>
+    where
+        T: MessageDefinition<T>,
+    {
+        // Like subscriptions, events are only executed on the thread spinning the node
+        let event = Rc::new(QoSEvent::new_for_subscription(
+            &subscription.handle,
+            callback,
+        )?);
+        self.events.borrow_mut().push(Rc::downgrade(&event));
+        Ok(event)
+    }
+
+    /// Registers a callback for a QoS event of `publisher`, e.g. an offered deadline it missed
+    ///
+    /// See [`Node::create_subscription_event`].
+    pub fn create_publisher_event<T>(
+        &self,
+        publisher: &Publisher<T>,
+        callback: PublisherEventCallback,
+    ) -> Result<Rc<QoSEvent>, RclReturnCode>
+    where
+        T: MessageDefinition<T>,
+    {
+        // Like subscriptions, events are only executed on the thread spinning the node
+        let event = Rc::new(QoSEvent::new_for_publisher(&publisher.handle, callback)?);
+        self.events.borrow_mut().push(Rc::downgrade(&event));
+        Ok(event)
+    }
+
+    /// Creates a subscription whose callback may return a value for every message it receives
+    ///
+    /// Returned values are collected, in order, into a queue holding at most `capacity`
+    /// values. See [`SubscriptionResults`] for what happens once the queue is full.
+    pub fn create_collecting_subscription<T, U, F>(
+        &self,
+        topic: &str,
+        qos: QoSProfile,
+        capacity: usize,
+        callback: F,
+    ) -> Result<CollectingSubscription<T, U>, RclReturnCode>
+    where
+        T: MessageDefinition<T> + Default,
+        U: 'static,
+        F: FnMut(&T) -> Option<U> + Sized + 'static,
+    {
+        let results = Arc::new(SubscriptionResults::new(capacity));
+        let subscription =
+            self.create_subscription(topic, qos, collect_into(results.clone(), callback))?;
+        Ok(CollectingSubscription::new(subscription, results))
+    }
+}
+
+fn fully_qualified_name(namespace: &str, name: &str) -> String {
+    if namespace.ends_with('/') {
+        format!("{}{}", namespace, name)
+    } else {
+        format!("{}/{}", namespace, name)
+    }
+}
+
+fn extend_name_with_sub_namespace(name: &str, sub_namespace: &str) -> String {
+    if sub_namespace.is_empty() || name.starts_with('/') || name.starts_with('~') {
+        name.to_string()
+    } else {
+        format!("{}/{}", sub_namespace, name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{extend_name_with_sub_namespace, fully_qualified_name};
+
+    #[test]
+    fn test_fully_qualified_name() {
+        assert_eq!(fully_qualified_name("/", "talker"), "/talker");
+        assert_eq!(fully_qualified_name("/robot", "talker"), "/robot/talker");
+    }
+
+    #[test]
+    fn test_relative_name_is_extended() {
+        assert_eq!(
+            extend_name_with_sub_namespace("odom", "wheels"),
+            "wheels/odom"
+        );
+        assert_eq!(
+            extend_name_with_sub_namespace("odom", "base/wheels"),
+            "base/wheels/odom"
+        );
+    }
+
+    #[test]
+    fn test_absolute_and_private_names_are_kept() {
+        assert_eq!(extend_name_with_sub_namespace("/odom", "wheels"), "/odom");
+        assert_eq!(extend_name_with_sub_namespace("~/odom", "wheels"), "~/odom");
+    }
+
+    #[test]
+    fn test_empty_sub_namespace() {
+        assert_eq!(extend_name_with_sub_namespace("odom", ""), "odom");
+    }
 }
@@ -1,12 +1,14 @@
+use crate::allocator::rcl_allocator;
 use crate::error::{RclReturnCode, ToResult};
 use crate::qos::QoSProfile;
 use crate::rcl_bindings::*;
 use crate::{Node, NodeHandle};
 use rclrs_msg_utilities::traits::MessageDefinition;
+use alloc::string::String;
 use alloc::sync::Arc;
 use core::borrow::Borrow;
 use core::marker::PhantomData;
-use cstr_core::CString;
+use cstr_core::{CStr, CString};
 
 #[cfg(not(feature = "std"))]
 use spin::{Mutex, MutexGuard};
@@ -28,7 +30,7 @@ impl PublisherHandle {
         self.handle.get_mut()
     }
 
-    fn lock(&self) -> MutexGuard<rcl_publisher_t> {
+    pub(crate) fn lock(&self) -> MutexGuard<rcl_publisher_t> {
         self.handle.lock()
     }
 
@@ -66,12 +68,13 @@ where
     {
         let mut publisher_handle = unsafe { rcl_get_zero_initialized_publisher() };
         let type_support = T::get_type_support() as *const rosidl_message_type_support_t;
-        let topic_c_string = CString::new(topic).unwrap();
+        let topic_c_string = CString::new(node.extend_name(topic)).unwrap();
         let node_handle = &mut *node.handle.lock();
 
         unsafe {
             let mut publisher_options = rcl_publisher_get_default_options();
             publisher_options.qos = qos.into();
+            publisher_options.allocator = rcl_allocator(node.allocator.as_ref());
 
             rcl_publisher_init(
                 &mut publisher_handle as *mut _,
@@ -107,4 +110,14 @@ where
         message.destroy_native_message(native_message_ptr);
         ret.ok()
     }
+
+    /// Fully qualified name of the topic, after expansion and remapping
+    pub fn topic_name(&self) -> String {
+        let handle = &*self.handle.lock();
+        unsafe {
+            CStr::from_ptr(rcl_publisher_get_topic_name(handle as *const _))
+                .to_string_lossy()
+                .into_owned()
+        }
+    }
 }
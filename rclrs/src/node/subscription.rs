@@ -1,12 +1,13 @@
+use crate::allocator::rcl_allocator;
 use crate::error::ToResult;
 use crate::qos::QoSProfile;
 use crate::{RclReturnCode, SubscriberErrorCode, rcl_bindings::*, to_rcl_result};
 use crate::{Node, NodeHandle};
 use rclrs_msg_utilities::traits::{Message, MessageDefinition};
 use alloc::boxed::Box;
+use alloc::collections::VecDeque;
 use alloc::sync::Arc;
 use core::borrow::Borrow;
-use core::marker::PhantomData;
 use cstr_core::CString;
 
 #[cfg(not(feature = "std"))]
@@ -48,56 +49,77 @@ impl Drop for SubscriptionHandle {
     }
 }
 
+/// Metadata the middleware reports alongside a received message
+#[derive(Clone, Debug, PartialEq)]
+pub struct MessageInfo {
+    /// When the message was published, in nanoseconds since the epoch
+    pub source_timestamp: i64,
+    /// When the message was received, in nanoseconds since the epoch
+    pub received_timestamp: i64,
+    /// Identifies the publisher that sent the message
+    pub publisher_gid: [u8; 24],
+    pub from_intra_process: bool,
+}
+
+impl MessageInfo {
+    fn from_rmw(message_info: &rmw_message_info_t) -> Self {
+        Self {
+            source_timestamp: message_info.source_timestamp,
+            received_timestamp: message_info.received_timestamp,
+            publisher_gid: message_info.publisher_gid.data,
+            from_intra_process: message_info.from_intra_process,
+        }
+    }
+}
+
 /// Trait to be implemented by concrete Subscriber structs
 /// See [`Subscription<T>`] for an example
 pub trait SubscriptionBase {
     fn handle(&self) -> &SubscriptionHandle;
-    fn create_message(&self) -> Box<dyn Message>;
-    fn callback_fn(&self, message: Box<dyn Message>) -> ();
 
-    /// Ask RMW for the data
+    /// Takes the next available message and passes it to the callback
     ///
-    /// +-------------+
-    /// | rclrs::take |
-    /// +------+------+
-    ///        |
-    ///        |
-    /// +------v------+
-    /// |  rcl_take   |
-    /// +------+------+
-    ///        |
-    ///        |
-    /// +------v------+
-    /// |  rmw_take   |
-    /// +-------------+
-    fn take(&self, message: &mut dyn Message) -> Result<bool, RclReturnCode> {
-        let handle = &mut *self.handle().lock();
-        let message_handle = message.get_native_message();
+    /// Returns whether a message was available.
+    fn execute(&self) -> Result<bool, RclReturnCode>;
+}
 
-        let result = unsafe {
-            rcl_take(
-                handle as *const _,
-                message_handle as *mut _,
-                core::ptr::null_mut(),
-                core::ptr::null_mut(),
-            )
-        };
+fn take_message(
+    handle: &SubscriptionHandle,
+    message: &mut dyn Message,
+    message_info: *mut rmw_message_info_t,
+) -> Result<bool, RclReturnCode> {
+    let handle = &mut *handle.lock();
+    let message_handle = message.get_native_message();
 
-        let result = match to_rcl_result(result) {
-            Ok(()) => {
-                message.read_handle(message_handle);
-                Ok(true)
-            }
-            Err(RclReturnCode::SubscriberError(SubscriberErrorCode::SubscriptionTakeFailed)) => {
-                Ok(false)
-            }
-            Err(error) => Err(error.into()),
-        };
+    let result = unsafe {
+        rcl_take(
+            handle as *const _,
+            message_handle as *mut _,
+            message_info,
+            core::ptr::null_mut(),
+        )
+    };
 
-        message.destroy_native_message(message_handle);
+    let result = match to_rcl_result(result) {
+        Ok(()) => {
+            message.read_handle(message_handle);
+            Ok(true)
+        }
+        Err(RclReturnCode::SubscriberError(SubscriberErrorCode::SubscriptionTakeFailed)) => {
+            Ok(false)
+        }
+        Err(error) => Err(error),
+    };
 
-        result
-    }
+    message.destroy_native_message(message_handle);
+
+    result
+}
+
+/// Callback of a [`Subscription`], with or without the [`MessageInfo`] of each message
+pub enum SubscriptionCallback<T> {
+    Regular(Box<dyn FnMut(&T) + 'static>),
+    WithInfo(Box<dyn FnMut(&T, MessageInfo) + 'static>),
 }
 
 /// Main class responsible for subscribing to topics and receiving data over IPC in ROS
@@ -107,8 +129,9 @@ where
 {
     pub handle: Arc<SubscriptionHandle>,
     // The callback's lifetime should last as long as we need it to
-    pub callback: Mutex<Box<dyn FnMut(&T) + 'static>>,
-    message: PhantomData<T>,
+    pub callback: Mutex<SubscriptionCallback<T>>,
+    // Reused by every take while spinning, instead of a new message per take
+    message: Mutex<T>,
 }
 
 impl<T> Subscription<T>
@@ -122,17 +145,54 @@ where
         callback: F,
     ) -> Result<Self, RclReturnCode>
     where
-        T: MessageDefinition<T>,
+        T: MessageDefinition<T> + Default,
         F: FnMut(&T) + Sized + 'static,
+    {
+        Self::new_with_callback(
+            node,
+            topic,
+            qos,
+            SubscriptionCallback::Regular(Box::new(callback)),
+        )
+    }
+
+    /// Like [`Subscription::new`], but `callback` also receives the [`MessageInfo`] of each message
+    pub fn new_with_info<F>(
+        node: &Node,
+        topic: &str,
+        qos: QoSProfile,
+        callback: F,
+    ) -> Result<Self, RclReturnCode>
+    where
+        T: MessageDefinition<T> + Default,
+        F: FnMut(&T, MessageInfo) + Sized + 'static,
+    {
+        Self::new_with_callback(
+            node,
+            topic,
+            qos,
+            SubscriptionCallback::WithInfo(Box::new(callback)),
+        )
+    }
+
+    fn new_with_callback(
+        node: &Node,
+        topic: &str,
+        qos: QoSProfile,
+        callback: SubscriptionCallback<T>,
+    ) -> Result<Self, RclReturnCode>
+    where
+        T: MessageDefinition<T> + Default,
     {
         let mut subscription_handle = unsafe { rcl_get_zero_initialized_subscription() };
         let type_support = T::get_type_support() as *const rosidl_message_type_support_t;
-        let topic_c_string = CString::new(topic).unwrap();
+        let topic_c_string = CString::new(node.extend_name(topic)).unwrap();
         let node_handle = &mut *node.handle.lock();
 
         unsafe {
             let mut subscription_options = rcl_subscription_get_default_options();
             subscription_options.qos = qos.into();
+            subscription_options.allocator = rcl_allocator(node.allocator.as_ref());
             rcl_subscription_init(
                 &mut subscription_handle as *mut _,
                 node_handle as *mut _,
@@ -150,8 +210,8 @@ where
 
         Ok(Self {
             handle,
-            callback: Mutex::new(Box::new(callback)),
-            message: PhantomData,
+            callback: Mutex::new(callback),
+            message: Mutex::new(T::default()),
         })
     }
 
@@ -171,16 +231,21 @@ where
         ret.ok().map_err(|err| err.into())
     }
 
-    fn callback_ext(
-        &self,
-        message: Box<dyn Message>,
-    ) -> Result<(), RclReturnCode> {
-        let msg = message.downcast_ref::<T>().unwrap();
-        let msg = message
-            .downcast_ref()
-            .unwrap();
-        (&mut *self.callback.lock())(msg);
-        Ok(())
+    /// Takes the next available message into `message`, with its [`MessageInfo`]
+    ///
+    /// Unlike [`Subscription::take`], this returns `None` and leaves `message` untouched when no
+    /// message is available, so one message object can be recycled in a fixed-rate loop. Note
+    /// that each call still converts through a temporary native message, so only fixed-size
+    /// fields are written in place; string and sequence fields are replaced by newly allocated
+    /// ones.
+    pub fn take_into(&self, message: &mut T) -> Result<Option<MessageInfo>, RclReturnCode> {
+        let mut message_info = unsafe { rmw_get_zero_initialized_message_info() };
+        let taken = take_message(&self.handle, message, &mut message_info as *mut _)?;
+        Ok(if taken {
+            Some(MessageInfo::from_rmw(&message_info))
+        } else {
+            None
+        })
     }
 }
 
@@ -192,11 +257,199 @@ where
         self.handle.borrow()
     }
 
-    fn create_message(&self) -> Box<dyn Message> {
-        Box::new(T::default())
+    /// The message info is only requested from the middleware when the callback uses it
+    fn execute(&self) -> Result<bool, RclReturnCode> {
+        let message = &mut *self.message.lock();
+        let callback = &mut *self.callback.lock();
+        match callback {
+            SubscriptionCallback::Regular(callback) => {
+                if !take_message(&self.handle, message, core::ptr::null_mut())? {
+                    return Ok(false);
+                }
+                callback(message);
+            }
+            SubscriptionCallback::WithInfo(callback) => match self.take_into(message)? {
+                Some(message_info) => callback(message, message_info),
+                None => return Ok(false),
+            },
+        }
+        Ok(true)
+    }
+}
+
+/// Bounded queue of the values returned by a collecting subscription's callback
+///
+/// Values are queued in the order their messages were taken while spinning. Once `capacity`
+/// values are waiting, the oldest one is discarded to make room for the newest, the same way
+/// the `KeepLast` history policy treats messages. A capacity of zero discards every value.
+pub struct SubscriptionResults<U> {
+    queue: Mutex<VecDeque<U>>,
+    capacity: usize,
+}
+
+impl<U> SubscriptionResults<U> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            queue: Mutex::new(VecDeque::with_capacity(capacity)),
+            capacity,
+        }
+    }
+
+    /// Removes and returns the oldest queued value, if there is one
+    pub fn try_recv(&self) -> Option<U> {
+        self.queue.lock().pop_front()
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.lock().is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
     }
 
-    fn callback_fn(&self, message: Box<dyn Message>) {
-        self.callback_ext(message);
+    fn push(&self, value: U) {
+        if self.capacity == 0 {
+            return;
+        }
+        let queue = &mut *self.queue.lock();
+        if queue.len() == self.capacity {
+            queue.pop_front();
+        }
+        queue.push_back(value);
+    }
+}
+
+/// Subscription whose callback transforms each message into a value collected for the user
+///
+/// The callback runs on the spinning thread, just like a regular [`Subscription`] callback.
+/// Every `Some` it returns is pushed into [`SubscriptionResults`]. The queue is `Send` and `Sync`
+/// when `U` is `Send`, so the `Arc` returned by [`CollectingSubscription::results`] can be moved
+/// to another thread and drained there with [`SubscriptionResults::try_recv`].
+pub struct CollectingSubscription<T, U>
+where
+    T: Message,
+{
+    pub subscription: Arc<Subscription<T>>,
+    results: Arc<SubscriptionResults<U>>,
+}
+
+impl<T, U> CollectingSubscription<T, U>
+where
+    T: Message,
+{
+    pub(crate) fn new(
+        subscription: Arc<Subscription<T>>,
+        results: Arc<SubscriptionResults<U>>,
+    ) -> Self {
+        Self {
+            subscription,
+            results,
+        }
+    }
+
+    pub fn results(&self) -> Arc<SubscriptionResults<U>> {
+        self.results.clone()
+    }
+}
+
+pub(crate) fn collect_into<T, U, F>(
+    results: Arc<SubscriptionResults<U>>,
+    mut callback: F,
+) -> impl FnMut(&T) + 'static
+where
+    T: 'static,
+    U: 'static,
+    F: FnMut(&T) -> Option<U> + 'static,
+{
+    move |message: &T| {
+        if let Some(value) = callback(message) {
+            results.push(value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{collect_into, SubscriptionResults};
+    use alloc::sync::Arc;
+
+    #[test]
+    fn test_results_preserve_order() {
+        let results = Arc::new(SubscriptionResults::new(4));
+        let mut callback = collect_into(results.clone(), |value: &i32| Some(value * 10));
+        for value in 1..=3 {
+            callback(&value);
+        }
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(results.try_recv(), Some(10));
+        assert_eq!(results.try_recv(), Some(20));
+        assert_eq!(results.try_recv(), Some(30));
+        assert_eq!(results.try_recv(), None);
+    }
+
+    #[test]
+    fn test_results_skip_none() {
+        let results = Arc::new(SubscriptionResults::new(4));
+        let mut callback = collect_into(results.clone(), |value: &i32| {
+            if value % 2 == 0 {
+                Some(*value)
+            } else {
+                None
+            }
+        });
+        for value in 1..=4 {
+            callback(&value);
+        }
+
+        assert_eq!(results.try_recv(), Some(2));
+        assert_eq!(results.try_recv(), Some(4));
+        assert!(results.is_empty());
+    }
+
+    #[test]
+    fn test_results_drop_oldest_when_full() {
+        let results = SubscriptionResults::new(2);
+        results.push(1);
+        results.push(2);
+        results.push(3);
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results.try_recv(), Some(2));
+        assert_eq!(results.try_recv(), Some(3));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_results_drained_from_another_thread() {
+        let results = Arc::new(SubscriptionResults::new(4));
+        let mut callback = collect_into(results.clone(), |value: &i32| Some(*value));
+        callback(&1);
+        callback(&2);
+
+        let drained = std::thread::spawn(move || {
+            let mut drained = alloc::vec::Vec::new();
+            while let Some(value) = results.try_recv() {
+                drained.push(value);
+            }
+            drained
+        })
+        .join()
+        .unwrap();
+
+        assert_eq!(drained, [1, 2]);
+    }
+
+    #[test]
+    fn test_results_zero_capacity() {
+        let results = SubscriptionResults::new(0);
+        results.push(1);
+
+        assert!(results.is_empty());
     }
 }
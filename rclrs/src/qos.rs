@@ -1,36 +1,160 @@
 use crate::rcl_bindings::*;
+use core::time::Duration;
 
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum QoSReliabilityPolicy {
     SystemDefault = 0,
     Reliable = 1,
     BestEffort = 2,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum QoSHistoryPolicy {
     SystemDefault = 0,
     KeepLast = 1,
     KeepAll = 2,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum QoSDurabilityPolicy {
     SystemDefault = 0,
     TransientLocal = 1,
     Volatile = 2,
 }
 
+/// How the middleware decides whether a publisher is still alive
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum QoSLivelinessPolicy {
+    SystemDefault = 0,
+    /// Any activity of the publisher's node asserts its liveliness
+    Automatic = 1,
+    /// Only publishing on the topic asserts the publisher's liveliness
+    ManualByTopic = 3,
+}
+
+/// Quality of service settings of a publisher or subscription
+///
+/// Start from one of the presets and override what needs to differ, e.g.
+/// `QoSProfile::sensor_data().keep_last(20).reliable()`. Durations of zero leave the
+/// policy to the middleware's default.
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct QoSProfile {
     pub history: QoSHistoryPolicy,
     pub depth: isize,
     pub reliability: QoSReliabilityPolicy,
     pub durability: QoSDurabilityPolicy,
+    /// Maximum expected period between messages
+    pub deadline: Duration,
+    /// Age after which a message is no longer delivered
+    pub lifespan: Duration,
+    pub liveliness: QoSLivelinessPolicy,
+    /// Period within which a publisher must assert its liveliness
+    pub liveliness_lease_duration: Duration,
     pub avoid_ros_namespace_conventions: bool,
 }
 
+impl QoSProfile {
+    pub fn sensor_data() -> Self {
+        QOS_PROFILE_SENSOR_DATA
+    }
+
+    pub fn parameters() -> Self {
+        QOS_PROFILE_PARAMETERS
+    }
+
+    pub fn topics_default() -> Self {
+        QOS_PROFILE_DEFAULT
+    }
+
+    pub fn services_default() -> Self {
+        QOS_PROFILE_SERVICES_DEFAULT
+    }
+
+    pub fn parameter_events() -> Self {
+        QOS_PROFILE_PARAMETER_EVENTS
+    }
+
+    pub fn system_default() -> Self {
+        QOS_PROFILE_SYSTEM_DEFAULT
+    }
+
+    /// Keeps up to `depth` messages, dropping the oldest ones
+    pub fn keep_last(mut self, depth: isize) -> Self {
+        self.history = QoSHistoryPolicy::KeepLast;
+        self.depth = depth;
+        self
+    }
+
+    /// Keeps every message, up to the middleware's resource limits
+    pub fn keep_all(mut self) -> Self {
+        self.history = QoSHistoryPolicy::KeepAll;
+        self
+    }
+
+    pub fn reliable(mut self) -> Self {
+        self.reliability = QoSReliabilityPolicy::Reliable;
+        self
+    }
+
+    pub fn best_effort(mut self) -> Self {
+        self.reliability = QoSReliabilityPolicy::BestEffort;
+        self
+    }
+
+    pub fn volatile(mut self) -> Self {
+        self.durability = QoSDurabilityPolicy::Volatile;
+        self
+    }
+
+    /// Makes a publisher keep messages for subscriptions that join late
+    pub fn transient_local(mut self) -> Self {
+        self.durability = QoSDurabilityPolicy::TransientLocal;
+        self
+    }
+
+    pub fn deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = deadline;
+        self
+    }
+
+    pub fn lifespan(mut self, lifespan: Duration) -> Self {
+        self.lifespan = lifespan;
+        self
+    }
+
+    pub fn liveliness(mut self, liveliness: QoSLivelinessPolicy) -> Self {
+        self.liveliness = liveliness;
+        self
+    }
+
+    pub fn liveliness_lease_duration(mut self, lease_duration: Duration) -> Self {
+        self.liveliness_lease_duration = lease_duration;
+        self
+    }
+
+    pub fn avoid_ros_namespace_conventions(mut self, avoid: bool) -> Self {
+        self.avoid_ros_namespace_conventions = avoid;
+        self
+    }
+}
+
+impl Default for QoSProfile {
+    fn default() -> Self {
+        QOS_PROFILE_DEFAULT
+    }
+}
+
+const DURATION_DEFAULT: Duration = Duration::from_secs(0);
+
 pub const QOS_PROFILE_SENSOR_DATA: QoSProfile = QoSProfile {
     history: QoSHistoryPolicy::KeepLast,
     depth: 5,
     reliability: QoSReliabilityPolicy::BestEffort,
     durability: QoSDurabilityPolicy::Volatile,
+    deadline: DURATION_DEFAULT,
+    lifespan: DURATION_DEFAULT,
+    liveliness: QoSLivelinessPolicy::SystemDefault,
+    liveliness_lease_duration: DURATION_DEFAULT,
     avoid_ros_namespace_conventions: false,
 };
 
@@ -39,6 +163,10 @@ pub const QOS_PROFILE_PARAMETERS: QoSProfile = QoSProfile {
     depth: 1000,
     reliability: QoSReliabilityPolicy::Reliable,
     durability: QoSDurabilityPolicy::Volatile,
+    deadline: DURATION_DEFAULT,
+    lifespan: DURATION_DEFAULT,
+    liveliness: QoSLivelinessPolicy::SystemDefault,
+    liveliness_lease_duration: DURATION_DEFAULT,
     avoid_ros_namespace_conventions: false,
 };
 
@@ -47,6 +175,10 @@ pub const QOS_PROFILE_DEFAULT: QoSProfile = QoSProfile {
     depth: 10,
     reliability: QoSReliabilityPolicy::Reliable,
     durability: QoSDurabilityPolicy::Volatile,
+    deadline: DURATION_DEFAULT,
+    lifespan: DURATION_DEFAULT,
+    liveliness: QoSLivelinessPolicy::SystemDefault,
+    liveliness_lease_duration: DURATION_DEFAULT,
     avoid_ros_namespace_conventions: false,
 };
 
@@ -55,6 +187,10 @@ pub const QOS_PROFILE_SERVICES_DEFAULT: QoSProfile = QoSProfile {
     depth: 10,
     reliability: QoSReliabilityPolicy::Reliable,
     durability: QoSDurabilityPolicy::Volatile,
+    deadline: DURATION_DEFAULT,
+    lifespan: DURATION_DEFAULT,
+    liveliness: QoSLivelinessPolicy::SystemDefault,
+    liveliness_lease_duration: DURATION_DEFAULT,
     avoid_ros_namespace_conventions: false,
 };
 
@@ -63,6 +199,10 @@ pub const QOS_PROFILE_PARAMETER_EVENTS: QoSProfile = QoSProfile {
     depth: 1000,
     reliability: QoSReliabilityPolicy::Reliable,
     durability: QoSDurabilityPolicy::Volatile,
+    deadline: DURATION_DEFAULT,
+    lifespan: DURATION_DEFAULT,
+    liveliness: QoSLivelinessPolicy::SystemDefault,
+    liveliness_lease_duration: DURATION_DEFAULT,
     avoid_ros_namespace_conventions: false,
 };
 
@@ -73,6 +213,10 @@ pub const QOS_PROFILE_SYSTEM_DEFAULT: QoSProfile = QoSProfile {
     depth: SYSTEM_DEFAULT,
     reliability: QoSReliabilityPolicy::SystemDefault,
     durability: QoSDurabilityPolicy::SystemDefault,
+    deadline: DURATION_DEFAULT,
+    lifespan: DURATION_DEFAULT,
+    liveliness: QoSLivelinessPolicy::SystemDefault,
+    liveliness_lease_duration: DURATION_DEFAULT,
     avoid_ros_namespace_conventions: false,
 };
 
@@ -84,10 +228,33 @@ impl From<QoSProfile> for rmw_qos_profile_t {
             reliability: qos.reliability.into(),
             durability: qos.durability.into(),
             avoid_ros_namespace_conventions: qos.avoid_ros_namespace_conventions,
-            deadline: rmw_time_t { sec: 0, nsec: 0 },
-            lifespan: rmw_time_t { sec: 0, nsec: 0 },
-            liveliness_lease_duration: rmw_time_t { sec: 0, nsec: 0 },
-            liveliness: rmw_qos_liveliness_policy_t::RMW_QOS_POLICY_LIVELINESS_SYSTEM_DEFAULT,
+            deadline: to_rmw_time(qos.deadline),
+            lifespan: to_rmw_time(qos.lifespan),
+            liveliness_lease_duration: to_rmw_time(qos.liveliness_lease_duration),
+            liveliness: qos.liveliness.into(),
+        }
+    }
+}
+
+fn to_rmw_time(duration: Duration) -> rmw_time_t {
+    rmw_time_t {
+        sec: duration.as_secs(),
+        nsec: duration.subsec_nanos() as u64,
+    }
+}
+
+impl From<QoSLivelinessPolicy> for rmw_qos_liveliness_policy_t {
+    fn from(policy: QoSLivelinessPolicy) -> Self {
+        match policy {
+            QoSLivelinessPolicy::SystemDefault => {
+                rmw_qos_liveliness_policy_t::RMW_QOS_POLICY_LIVELINESS_SYSTEM_DEFAULT
+            }
+            QoSLivelinessPolicy::Automatic => {
+                rmw_qos_liveliness_policy_t::RMW_QOS_POLICY_LIVELINESS_AUTOMATIC
+            }
+            QoSLivelinessPolicy::ManualByTopic => {
+                rmw_qos_liveliness_policy_t::RMW_QOS_POLICY_LIVELINESS_MANUAL_BY_TOPIC
+            }
         }
     }
 }
@@ -137,3 +304,55 @@ impl From<QoSDurabilityPolicy> for rmw_qos_durability_policy_t {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preset_with_overrides() {
+        let qos = QoSProfile::sensor_data()
+            .keep_last(20)
+            .reliable()
+            .transient_local()
+            .deadline(Duration::from_millis(100));
+
+        assert_eq!(qos.history, QoSHistoryPolicy::KeepLast);
+        assert_eq!(qos.depth, 20);
+        assert_eq!(qos.reliability, QoSReliabilityPolicy::Reliable);
+        assert_eq!(qos.durability, QoSDurabilityPolicy::TransientLocal);
+        assert_eq!(qos.deadline, Duration::from_millis(100));
+        // Untouched fields keep the preset's values
+        assert_eq!(qos.lifespan, QOS_PROFILE_SENSOR_DATA.lifespan);
+        assert_eq!(qos.liveliness, QOS_PROFILE_SENSOR_DATA.liveliness);
+    }
+
+    #[test]
+    fn test_conversion_to_rmw() {
+        let qos: rmw_qos_profile_t = QoSProfile::default()
+            .keep_all()
+            .lifespan(Duration::new(2, 500))
+            .liveliness(QoSLivelinessPolicy::ManualByTopic)
+            .liveliness_lease_duration(Duration::from_secs(1))
+            .into();
+
+        assert!(matches!(
+            qos.history,
+            rmw_qos_history_policy_t::RMW_QOS_POLICY_HISTORY_KEEP_ALL
+        ));
+        assert_eq!(qos.depth, 10);
+        assert_eq!((qos.deadline.sec, qos.deadline.nsec), (0, 0));
+        assert_eq!((qos.lifespan.sec, qos.lifespan.nsec), (2, 500));
+        assert!(matches!(
+            qos.liveliness,
+            rmw_qos_liveliness_policy_t::RMW_QOS_POLICY_LIVELINESS_MANUAL_BY_TOPIC
+        ));
+        assert_eq!(
+            (
+                qos.liveliness_lease_duration.sec,
+                qos.liveliness_lease_duration.nsec
+            ),
+            (1, 0)
+        );
+    }
+}
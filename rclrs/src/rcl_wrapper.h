@@ -1,2 +1,6 @@
 #include <rcl/rcl.h>
-#include <rcutils/error_handling.h>
\ No newline at end of file
+#include <rcl/graph.h>
+#include <rcutils/error_handling.h>
+#include <rcutils/logging.h>
+#include <rcutils/shared_library.h>
+#include <rmw/rmw.h>
\ No newline at end of file
@@ -0,0 +1,142 @@
+use crate::error::{RclReturnCode, ToResult};
+use crate::rcl_bindings::*;
+use core::slice;
+use rclrs_msg_utilities::traits::MessageDefinition;
+
+/// A message in the middleware's serialized wire format (CDR for the DDS-based rmw implementations)
+pub struct SerializedMessage {
+    handle: rmw_serialized_message_t,
+}
+
+impl SerializedMessage {
+    /// Creates an empty serialized message with room for `capacity` bytes
+    ///
+    /// The buffer grows as needed when a message is serialized into it.
+    pub fn with_capacity(capacity: usize) -> Result<Self, RclReturnCode> {
+        let mut handle = unsafe { rcutils_get_zero_initialized_uint8_array() };
+        unsafe {
+            let allocator = rcutils_get_default_allocator();
+            rcutils_uint8_array_init(&mut handle as *mut _, capacity, &allocator as *const _)
+                .ok()?;
+        }
+        Ok(Self { handle })
+    }
+
+    /// Copies already-serialized bytes, e.g. received over a custom transport
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, RclReturnCode> {
+        let mut message = Self::with_capacity(bytes.len())?;
+        // An empty message has no buffer to copy into
+        if bytes.is_empty() {
+            return Ok(message);
+        }
+        unsafe {
+            core::ptr::copy_nonoverlapping(bytes.as_ptr(), message.handle.buffer, bytes.len());
+        }
+        message.handle.buffer_length = bytes.len();
+        Ok(message)
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        if self.handle.buffer.is_null() {
+            return &[];
+        }
+        unsafe { slice::from_raw_parts(self.handle.buffer, self.handle.buffer_length) }
+    }
+
+    pub fn len(&self) -> usize {
+        self.handle.buffer_length
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.handle.buffer_length == 0
+    }
+}
+
+impl Drop for SerializedMessage {
+    fn drop(&mut self) {
+        unsafe {
+            rcutils_uint8_array_fini(&mut self.handle as *mut _);
+        }
+    }
+}
+
+/// Serializes `message` without going through a publisher
+///
+/// +--------------------------+
+/// | rclrs::serialize_message |
+/// +------------+-------------+
+///              |
+///              |
+///     +--------v--------+
+///     |  rmw_serialize  |
+///     +-----------------+
+pub fn serialize_message<T>(message: &T) -> Result<SerializedMessage, RclReturnCode>
+where
+    T: MessageDefinition<T>,
+{
+    let mut serialized_message = SerializedMessage::with_capacity(0)?;
+    let type_support = T::get_type_support() as *const rosidl_message_type_support_t;
+    let native_message_ptr = message.get_native_message();
+    let ret = unsafe {
+        rmw_serialize(
+            native_message_ptr as *const _,
+            type_support,
+            &mut serialized_message.handle as *mut _,
+        )
+    };
+    message.destroy_native_message(native_message_ptr);
+    ret.ok()?;
+    Ok(serialized_message)
+}
+
+/// Deserializes a message of type `T` without going through a subscription
+///
+/// +----------------------------+
+/// | rclrs::deserialize_message |
+/// +-------------+--------------+
+///               |
+///               |
+///     +---------v---------+
+///     |  rmw_deserialize  |
+///     +-------------------+
+pub fn deserialize_message<T>(serialized_message: &SerializedMessage) -> Result<T, RclReturnCode>
+where
+    T: MessageDefinition<T> + Default,
+{
+    let mut message = T::default();
+    let type_support = T::get_type_support() as *const rosidl_message_type_support_t;
+    let native_message_ptr = message.get_native_message();
+    let ret = unsafe {
+        rmw_deserialize(
+            &serialized_message.handle as *const _,
+            type_support,
+            native_message_ptr as *mut _,
+        )
+    };
+    if ret.ok().is_ok() {
+        message.read_handle(native_message_ptr);
+    }
+    message.destroy_native_message(native_message_ptr);
+    ret.ok()?;
+    Ok(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SerializedMessage;
+
+    #[test]
+    fn test_bytes_round_trip() {
+        let bytes = [0x00, 0x01, 0x00, 0x00, 0x2a, 0x00, 0x00, 0x00];
+        let message = SerializedMessage::from_bytes(&bytes).unwrap();
+        assert_eq!(message.len(), bytes.len());
+        assert_eq!(message.as_bytes(), &bytes);
+    }
+
+    #[test]
+    fn test_empty_bytes_round_trip() {
+        let message = SerializedMessage::from_bytes(&[]).unwrap();
+        assert!(message.is_empty());
+        assert_eq!(message.as_bytes(), &[] as &[u8]);
+    }
+}
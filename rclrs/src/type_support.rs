@@ -0,0 +1,211 @@
+use crate::error::ToResult;
+use crate::rcl_bindings::*;
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::{String, ToString};
+use core::fmt::{self, Display};
+use core_error::Error;
+use cstr_core::CString;
+use libc::uintptr_t;
+
+#[cfg(not(feature = "std"))]
+use spin::Mutex;
+
+#[cfg(feature = "std")]
+use parking_lot::{const_mutex, Mutex};
+
+/// Type support of a message type that was looked up by name at runtime
+///
+/// The wrapped value has the same meaning as the one returned by
+/// `MessageDefinition::get_type_support`, so statically and dynamically typed entities can
+/// be created from the same kind of handle.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TypeSupportHandle {
+    type_support: uintptr_t,
+}
+
+impl TypeSupportHandle {
+    pub fn get(&self) -> uintptr_t {
+        self.type_support
+    }
+}
+
+/// Errors raised while looking up a message type's support library
+#[derive(Debug, PartialEq)]
+pub enum TypeSupportError {
+    /// The name is not of the form `package/msg/Type`
+    InvalidTypeName(String),
+    /// The `rosidl_typesupport_c` library of the package could not be loaded
+    LibraryNotFound(String),
+    /// The package's library was loaded, but it does not provide the type
+    TypeNotFound(String),
+}
+
+impl Display for TypeSupportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidTypeName(name) => {
+                write!(f, "TypeSupportError: `{}` is not a valid type name!", name)
+            }
+            Self::LibraryNotFound(package) => write!(
+                f,
+                "TypeSupportError: Unable to load the type support library of package `{}`!",
+                package
+            ),
+            Self::TypeNotFound(name) => write!(
+                f,
+                "TypeSupportError: No type support found for type `{}`!",
+                name
+            ),
+        }
+    }
+}
+
+impl Error for TypeSupportError {}
+
+/// The parts of a fully qualified type name, e.g. `std_msgs/msg/String`
+#[derive(Debug, PartialEq)]
+struct TypeName<'a> {
+    package: &'a str,
+    interface: &'a str,
+    name: &'a str,
+}
+
+impl<'a> TypeName<'a> {
+    fn parse(type_name: &'a str) -> Result<Self, TypeSupportError> {
+        let mut parts = type_name.split('/');
+        match (parts.next(), parts.next(), parts.next(), parts.next()) {
+            (Some(package), Some(interface), Some(name), None)
+                if !package.is_empty()
+                    && !interface.is_empty()
+                    && !name.is_empty()
+                    && !type_name.contains('\0') =>
+            {
+                Ok(Self {
+                    package,
+                    interface,
+                    name,
+                })
+            }
+            _ => Err(TypeSupportError::InvalidTypeName(type_name.to_string())),
+        }
+    }
+
+    fn library_file_name(&self) -> String {
+        #[cfg(target_os = "windows")]
+        return format!("{}__rosidl_typesupport_c.dll", self.package);
+        #[cfg(target_os = "macos")]
+        return format!("lib{}__rosidl_typesupport_c.dylib", self.package);
+        #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+        return format!("lib{}__rosidl_typesupport_c.so", self.package);
+    }
+
+    fn symbol_name(&self) -> String {
+        format!(
+            "rosidl_typesupport_c__get_message_type_support_handle__{}__{}__{}",
+            self.package, self.interface, self.name
+        )
+    }
+}
+
+// Every lookup goes through this registry, so each type's library is only searched once
+#[cfg(feature = "std")]
+static TYPE_SUPPORTS: Mutex<Option<BTreeMap<String, TypeSupportHandle>>> = const_mutex(None);
+
+#[cfg(not(feature = "std"))]
+static TYPE_SUPPORTS: Mutex<Option<BTreeMap<String, TypeSupportHandle>>> = Mutex::new(None);
+
+/// Looks up the type support of a message type from its name, e.g. `std_msgs/msg/String`
+///
+/// The first lookup of a type loads the `rosidl_typesupport_c` library of its package, which
+/// must be on the library search path. Results are cached for the lifetime of the process.
+pub fn lookup_type_support(type_name: &str) -> Result<TypeSupportHandle, TypeSupportError> {
+    let registry = &mut *TYPE_SUPPORTS.lock();
+    let registry = registry.get_or_insert_with(BTreeMap::new);
+    if let Some(handle) = registry.get(type_name) {
+        return Ok(*handle);
+    }
+
+    let handle = load_type_support(&TypeName::parse(type_name)?, type_name)?;
+    registry.insert(type_name.to_string(), handle);
+    Ok(handle)
+}
+
+fn load_type_support(
+    name: &TypeName,
+    type_name: &str,
+) -> Result<TypeSupportHandle, TypeSupportError> {
+    // Neither string can contain a nul byte, `TypeName::parse` rejected those
+    let library_file_name = CString::new(name.library_file_name()).unwrap();
+    let symbol_name = CString::new(name.symbol_name()).unwrap();
+    let mut library = unsafe { rcutils_get_zero_initialized_shared_library() };
+
+    unsafe {
+        if rcutils_load_shared_library(
+            &mut library as *mut _,
+            library_file_name.as_ptr(),
+            rcutils_get_default_allocator(),
+        )
+        .ok()
+        .is_err()
+        {
+            rcutils_reset_error();
+            return Err(TypeSupportError::LibraryNotFound(name.package.to_string()));
+        }
+
+        if !rcutils_has_symbol(&library as *const _, symbol_name.as_ptr()) {
+            rcutils_unload_shared_library(&mut library as *mut _);
+            return Err(TypeSupportError::TypeNotFound(type_name.to_string()));
+        }
+
+        let symbol = rcutils_get_symbol(&library as *const _, symbol_name.as_ptr());
+        let get_type_support: unsafe extern "C" fn() -> *const rosidl_message_type_support_t =
+            core::mem::transmute(symbol);
+
+        // The library is never unloaded, since the type support must outlive every entity
+        // created from it
+        Ok(TypeSupportHandle {
+            type_support: get_type_support() as uintptr_t,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{lookup_type_support, TypeName, TypeSupportError};
+    use alloc::string::ToString;
+
+    #[test]
+    fn test_parse_type_name() {
+        assert_eq!(
+            TypeName::parse("std_msgs/msg/String").unwrap(),
+            TypeName {
+                package: "std_msgs",
+                interface: "msg",
+                name: "String",
+            }
+        );
+        assert_eq!(
+            TypeName::parse("std_msgs/msg/String")
+                .unwrap()
+                .symbol_name(),
+            "rosidl_typesupport_c__get_message_type_support_handle__std_msgs__msg__String"
+        );
+    }
+
+    #[test]
+    fn test_invalid_type_name() {
+        for type_name in &[
+            "",
+            "String",
+            "std_msgs/String",
+            "std_msgs//String",
+            "a/b/c/d",
+        ] {
+            assert_eq!(
+                lookup_type_support(type_name),
+                Err(TypeSupportError::InvalidTypeName(type_name.to_string()))
+            );
+        }
+    }
+}
@@ -15,10 +15,12 @@
 // DISTRIBUTION A. Approved for public release; distribution unlimited.
 // OPSEC #4584.
 
+use crate::allocator::{rcl_allocator, Allocator};
 use crate::rcl_bindings::*;
-use crate::SubscriptionBase;
+use crate::{QoSEvent, SubscriptionBase};
 
 use alloc::sync::Weak;
+use alloc::vec::Vec;
 use core::borrow::BorrowMut;
 use core::fmt::Display;
 use core_error::Error;
@@ -49,9 +51,53 @@ impl From<RclReturnCode> for WaitSetErrorResponse {
 
 impl Error for WaitSetErrorResponse {}
 
+/// Number of entities of each kind, e.g. that a wait set has room for
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct WaitableCount {
+    pub subscriptions: usize,
+    pub guard_conditions: usize,
+    pub timers: usize,
+    pub clients: usize,
+    pub services: usize,
+    pub events: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WaitableKind {
+    Subscription,
+    GuardCondition,
+    Timer,
+    Client,
+    Service,
+    Event,
+}
+
+/// An occupied slot of a wait set
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WaitSetEntry {
+    pub kind: WaitableKind,
+    /// Position of the entity among the entities of its kind
+    pub index: usize,
+    /// Whether the entity was ready when the wait set was last waited on
+    pub ready: bool,
+}
+
+/// Read-only snapshot of a wait set's bookkeeping, see [`WaitSet::describe`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WaitSetDescription {
+    /// Number of slots of each kind
+    pub capacity: WaitableCount,
+    /// Number of entities of each kind added since the wait set was created or cleared
+    pub added: WaitableCount,
+    /// Every added entity, in the order it was added within its kind
+    pub entries: Vec<WaitSetEntry>,
+}
+
 pub struct WaitSet {
     pub wait_set: rcl_wait_set_t,
     initialized: bool,
+    added: WaitableCount,
+    waited: bool,
 }
 
 impl WaitSet {
@@ -67,10 +113,38 @@ impl WaitSet {
         number_of_services: usize,
         number_of_events: usize,
         context: &mut rcl_context_t,
+    ) -> Result<Self, WaitSetErrorResponse> {
+        Self::new_with_allocator(
+            number_of_subscriptions,
+            number_of_guard_conditions,
+            number_of_timers,
+            number_of_clients,
+            number_of_services,
+            number_of_events,
+            context,
+            None,
+        )
+    }
+
+    /// Same as [`WaitSet::new`], but `rcl` allocates the wait set's storage with `allocator`
+    ///
+    /// Passing `None` uses the rcutils default allocator.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new_with_allocator(
+        number_of_subscriptions: usize,
+        number_of_guard_conditions: usize,
+        number_of_timers: usize,
+        number_of_clients: usize,
+        number_of_services: usize,
+        number_of_events: usize,
+        context: &mut rcl_context_t,
+        allocator: Option<&Allocator>,
     ) -> Result<Self, WaitSetErrorResponse> {
         let mut waitset = Self {
             wait_set: unsafe { rcl_get_zero_initialized_wait_set() },
             initialized: false,
+            added: WaitableCount::default(),
+            waited: false,
         };
         unsafe {
             match to_rcl_result(rcl_wait_set_init(
@@ -82,7 +156,7 @@ impl WaitSet {
                 number_of_services,
                 number_of_events,
                 context,
-                rcutils_get_default_allocator(),
+                rcl_allocator(allocator),
             )) {
                 Ok(()) => {
                     waitset.initialized = true;
@@ -111,6 +185,8 @@ impl WaitSet {
         unsafe {
             // Whether or not we successfully clear, this WaitSet will count as uninitialized
             self.initialized = false;
+            self.added = WaitableCount::default();
+            self.waited = false;
             to_rcl_result(rcl_wait_set_clear(self.wait_set.borrow_mut() as *mut _))
                 .map_err(WaitSetErrorResponse::ReturnCode)
         }
@@ -128,18 +204,135 @@ impl WaitSet {
         if let Some(subscription) = subscription.upgrade() {
             let subscription_handle = &mut *subscription.handle().lock();
             unsafe {
-                return to_rcl_result(rcl_wait_set_add_subscription(
+                to_rcl_result(rcl_wait_set_add_subscription(
                     self.wait_set.borrow_mut() as *mut _,
                     subscription_handle as *const _,
                     core::ptr::null_mut(),
                 ))
-                .map_err(WaitSetErrorResponse::ReturnCode);
+                .map_err(WaitSetErrorResponse::ReturnCode)?;
             }
+            self.added.subscriptions += 1;
+            Ok(())
         } else {
             Err(WaitSetErrorResponse::DroppedSubscription)
         }
     }
 
+    /// Adds a QoS event to the WaitSet
+    ///
+    /// # Errors
+    /// - `WaitSetError::RclError` for any `rcl` errors that occur during the process
+    pub fn add_event(&mut self, event: &QoSEvent) -> Result<(), WaitSetErrorResponse> {
+        let event_handle = &*event.handle.lock();
+        unsafe {
+            to_rcl_result(rcl_wait_set_add_event(
+                self.wait_set.borrow_mut() as *mut _,
+                event_handle as *const _,
+                core::ptr::null_mut(),
+            ))
+            .map_err(WaitSetErrorResponse::ReturnCode)?;
+        }
+        self.added.events += 1;
+        Ok(())
+    }
+
+    /// Describes which entities are in the WaitSet, e.g. to find out why a callback never runs
+    ///
+    /// Entities are only marked ready once [`WaitSet::wait`] was called. See [`spin_once_described`]
+    /// to describe the wait set of a spin.
+    ///
+    /// [`spin_once_described`]: crate::spin_once_described
+    pub fn describe(&self) -> WaitSetDescription {
+        let wait_set = &self.wait_set;
+        let capacity = WaitableCount {
+            subscriptions: wait_set.size_of_subscriptions,
+            guard_conditions: wait_set.size_of_guard_conditions,
+            timers: wait_set.size_of_timers,
+            clients: wait_set.size_of_clients,
+            services: wait_set.size_of_services,
+            events: wait_set.size_of_events,
+        };
+
+        let mut entries = Vec::new();
+        unsafe {
+            describe_slots(
+                &mut entries,
+                WaitableKind::Subscription,
+                wait_set.subscriptions,
+                capacity.subscriptions,
+                self.added.subscriptions,
+                self.waited,
+            );
+            describe_slots(
+                &mut entries,
+                WaitableKind::GuardCondition,
+                wait_set.guard_conditions,
+                capacity.guard_conditions,
+                self.added.guard_conditions,
+                self.waited,
+            );
+            describe_slots(
+                &mut entries,
+                WaitableKind::Timer,
+                wait_set.timers,
+                capacity.timers,
+                self.added.timers,
+                self.waited,
+            );
+            describe_slots(
+                &mut entries,
+                WaitableKind::Client,
+                wait_set.clients,
+                capacity.clients,
+                self.added.clients,
+                self.waited,
+            );
+            describe_slots(
+                &mut entries,
+                WaitableKind::Service,
+                wait_set.services,
+                capacity.services,
+                self.added.services,
+                self.waited,
+            );
+            describe_slots(
+                &mut entries,
+                WaitableKind::Event,
+                wait_set.events,
+                capacity.events,
+                self.added.events,
+                self.waited,
+            );
+        }
+
+        WaitSetDescription {
+            capacity,
+            added: self.added,
+            entries,
+        }
+    }
+
+    /// Whether the subscription that was added `index`-th was ready when the WaitSet was last
+    /// waited on
+    ///
+    /// Always false before [`WaitSet::wait`] was called.
+    pub fn subscription_is_ready(&self, index: usize) -> bool {
+        self.waited
+            && unsafe {
+                slot_is_set(
+                    self.wait_set.subscriptions,
+                    self.wait_set.size_of_subscriptions,
+                    index,
+                )
+            }
+    }
+
+    /// Like [`WaitSet::subscription_is_ready`], for the event that was added `index`-th
+    pub fn event_is_ready(&self, index: usize) -> bool {
+        self.waited
+            && unsafe { slot_is_set(self.wait_set.events, self.wait_set.size_of_events, index) }
+    }
+
     /// Blocks until the WaitSet is ready, or until the timeout has been exceeded
     ///
     /// This function will collect the items in the rcl_wait_set_t and pass them
@@ -189,10 +382,44 @@ impl WaitSet {
     /// - `RclError::Timeout` if the timeout expired before something was ready
     /// - `RclError::Error` for an unspecified error
     pub fn wait(&mut self, timeout: i64) -> Result<(), RclReturnCode> {
-        unsafe { to_rcl_result(rcl_wait(self.wait_set.borrow_mut() as *mut _, timeout)) }
+        let result =
+            unsafe { to_rcl_result(rcl_wait(self.wait_set.borrow_mut() as *mut _, timeout)) };
+        // On timeout, rcl also sets every slot to NULL
+        self.waited = matches!(result, Ok(()) | Err(RclReturnCode::Timeout));
+        result
     }
 }
 
+/// Appends an entry for each of the `added` entities of a wait set's array of `kind` entities
+///
+/// Entities are added to consecutive slots, and waiting sets the slots of the entities that are
+/// not ready to NULL.
+///
+/// # Safety
+/// `slots` must be NULL or point to `size` elements.
+unsafe fn describe_slots<T>(
+    entries: &mut Vec<WaitSetEntry>,
+    kind: WaitableKind,
+    slots: *mut *const T,
+    size: usize,
+    added: usize,
+    waited: bool,
+) {
+    entries.extend((0..added).map(|index| WaitSetEntry {
+        kind,
+        index,
+        ready: waited && slot_is_set(slots, size, index),
+    }));
+}
+
+/// Whether slot `index` of a wait set's array of entities holds an entity
+///
+/// # Safety
+/// `slots` must be NULL or point to `size` elements.
+unsafe fn slot_is_set<T>(slots: *mut *const T, size: usize, index: usize) -> bool {
+    !slots.is_null() && index < size && !(*slots.add(index)).is_null()
+}
+
 impl Drop for WaitSet {
     /// Drops the WaitSet, and clears the memory
     ///
@@ -210,3 +437,87 @@ impl Drop for WaitSet {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{describe_slots, slot_is_set, WaitSetEntry, WaitableKind};
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_describe_slots_marks_ready() {
+        let (first, second) = (1u8, 2u8);
+        let mut slots: [*const u8; 3] = [&first, core::ptr::null(), &second];
+        let mut entries = Vec::new();
+        unsafe {
+            describe_slots(
+                &mut entries,
+                WaitableKind::Subscription,
+                slots.as_mut_ptr(),
+                3,
+                3,
+                true,
+            );
+        }
+
+        let ready: Vec<_> = entries
+            .iter()
+            .map(|entry| (entry.index, entry.ready))
+            .collect();
+        assert_eq!(ready, [(0, true), (1, false), (2, true)]);
+    }
+
+    #[test]
+    fn test_describe_slots_before_waiting() {
+        let first = 1u8;
+        let mut slots: [*const u8; 2] = [&first, core::ptr::null()];
+        let mut entries = Vec::new();
+        unsafe {
+            describe_slots(
+                &mut entries,
+                WaitableKind::Event,
+                slots.as_mut_ptr(),
+                2,
+                1,
+                false,
+            );
+        }
+
+        assert_eq!(
+            entries,
+            [WaitSetEntry {
+                kind: WaitableKind::Event,
+                index: 0,
+                ready: false,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_describe_unallocated_slots() {
+        let mut entries = Vec::new();
+        unsafe {
+            describe_slots::<u8>(
+                &mut entries,
+                WaitableKind::Event,
+                core::ptr::null_mut(),
+                0,
+                0,
+                false,
+            );
+        }
+
+        assert!(entries.is_empty());
+    }
+
+    #[test]
+    fn test_slot_is_set() {
+        let ready = 1u8;
+        let mut slots: [*const u8; 2] = [core::ptr::null(), &ready];
+        unsafe {
+            assert!(!slot_is_set(slots.as_mut_ptr(), 2, 0));
+            assert!(slot_is_set(slots.as_mut_ptr(), 2, 1));
+            assert!(!slot_is_set(slots.as_mut_ptr(), 2, 2));
+            assert!(!slot_is_set::<u8>(core::ptr::null_mut(), 0, 0));
+        }
+    }
+}
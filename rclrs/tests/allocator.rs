@@ -0,0 +1,72 @@
+// These tests need a ROS 2 installation, they are run by `colcon test`
+
+use rclrs::wait::WaitSet;
+use rclrs::{Allocator, Context};
+use std::ffi::c_void;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::vec::Vec;
+
+#[derive(Default)]
+struct Counts {
+    allocations: AtomicUsize,
+    deallocations: AtomicUsize,
+}
+
+unsafe fn counts<'a>(state: *mut c_void) -> &'a Counts {
+    &*(state as *const Counts)
+}
+
+unsafe extern "C" fn allocate(size: usize, state: *mut c_void) -> *mut c_void {
+    counts(state).allocations.fetch_add(1, Ordering::SeqCst);
+    libc::malloc(size)
+}
+
+unsafe extern "C" fn deallocate(pointer: *mut c_void, state: *mut c_void) {
+    counts(state).deallocations.fetch_add(1, Ordering::SeqCst);
+    libc::free(pointer)
+}
+
+unsafe extern "C" fn reallocate(
+    pointer: *mut c_void,
+    size: usize,
+    state: *mut c_void,
+) -> *mut c_void {
+    counts(state).allocations.fetch_add(1, Ordering::SeqCst);
+    libc::realloc(pointer, size)
+}
+
+unsafe extern "C" fn zero_allocate(
+    number_of_elements: usize,
+    size_of_element: usize,
+    state: *mut c_void,
+) -> *mut c_void {
+    counts(state).allocations.fetch_add(1, Ordering::SeqCst);
+    libc::calloc(number_of_elements, size_of_element)
+}
+
+#[test]
+fn test_wait_set_storage_uses_the_allocator() {
+    let counts = Counts::default();
+    let allocator = unsafe {
+        Allocator::from_raw_parts(
+            allocate,
+            deallocate,
+            reallocate,
+            zero_allocate,
+            &counts as *const _ as *mut c_void,
+        )
+    };
+    let context = Context::builder(Vec::new()).build().unwrap();
+
+    let deallocations_before_drop = {
+        let context_handle = &mut *context.handle.lock();
+        let _wait_set =
+            WaitSet::new_with_allocator(1, 0, 0, 0, 0, 1, context_handle, Some(&allocator))
+                .unwrap();
+        assert!(counts.allocations.load(Ordering::SeqCst) > 0);
+        counts.deallocations.load(Ordering::SeqCst)
+    };
+
+    // Finalizing the wait set frees its storage through the same allocator
+    assert!(counts.deallocations.load(Ordering::SeqCst) > deallocations_before_drop);
+}
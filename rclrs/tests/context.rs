@@ -0,0 +1,48 @@
+// These tests need a ROS 2 installation, they are run by `colcon test`
+
+use rclrs::{Context, Node};
+use std::thread;
+use std::time::Duration;
+
+// Keeps the tests away from other ROS applications running on the same machine
+const TEST_DOMAIN_ID: usize = 42;
+
+/// Polls the graph seen by `node` until `is_done` returns true or about two seconds pass
+fn wait_for_node_names(node: &Node, is_done: impl Fn(&[String]) -> bool) -> Vec<String> {
+    let mut node_names = node.get_node_names().unwrap();
+    for _ in 0..20 {
+        if is_done(&node_names) {
+            break;
+        }
+        thread::sleep(Duration::from_millis(100));
+        node_names = node.get_node_names().unwrap();
+    }
+    node_names
+}
+
+#[test]
+fn test_sequential_contexts_are_isolated() {
+    {
+        let context = Context::builder(Vec::new())
+            .domain_id(TEST_DOMAIN_ID)
+            .build()
+            .unwrap();
+        let node = Node::new("first_context_node", &context).unwrap();
+        let node_names = wait_for_node_names(&node, |names| {
+            names.iter().any(|name| name == "/first_context_node")
+        });
+        assert!(node_names.iter().any(|name| name == "/first_context_node"));
+        // The node is dropped before the context, which then shuts down and finalizes itself
+    }
+
+    let context = Context::builder(Vec::new())
+        .domain_id(TEST_DOMAIN_ID)
+        .build()
+        .unwrap();
+    let node = Node::new("second_context_node", &context).unwrap();
+    // Give discovery the same amount of time it needed to find the first node
+    let node_names = wait_for_node_names(&node, |_| false);
+
+    assert!(node_names.iter().any(|name| name == "/second_context_node"));
+    assert!(!node_names.iter().any(|name| name == "/first_context_node"));
+}
@@ -11,7 +11,7 @@ fn main() -> Result<(), Error> {
         .collect();
     let context = rclrs::Context::default(args);
 
-    let mut node = context.create_node("minimal_subscriber")?;
+    let node = context.create_node("minimal_subscriber")?;
 
     let mut num_messages: usize = 0;
 
@@ -0,0 +1 @@
+//! Integration tests of rclrs that need message types, see the `tests` directory
@@ -0,0 +1,48 @@
+// These tests need a ROS 2 installation, they are run by `colcon test`
+
+use cstr_core::CString;
+use rclrs::{Context, Node, NodeOptions, QOS_PROFILE_DEFAULT};
+
+fn topic_name(node: &Node) -> String {
+    node.create_publisher::<std_msgs::msg::String>("topic", QOS_PROFILE_DEFAULT)
+        .unwrap()
+        .topic_name()
+}
+
+#[test]
+fn test_global_arguments_can_be_ignored() {
+    let args = ["node_options", "--ros-args", "-r", "topic:=remapped"]
+        .iter()
+        .map(|arg| CString::new(*arg).unwrap())
+        .collect();
+    let context = Context::builder(args).build().unwrap();
+
+    let default_node =
+        Node::new_with_options("default_node", "", &context, NodeOptions::new()).unwrap();
+    assert_eq!(topic_name(&default_node), "/remapped");
+
+    let isolated_node = Node::new_with_options(
+        "isolated_node",
+        "",
+        &context,
+        NodeOptions::new().use_global_arguments(false),
+    )
+    .unwrap();
+    assert_eq!(topic_name(&isolated_node), "/topic");
+
+    // Arguments given to the node itself still apply
+    let local_arguments = ["--ros-args", "-r", "topic:=local"]
+        .iter()
+        .map(|arg| arg.to_string())
+        .collect();
+    let local_node = Node::new_with_options(
+        "local_node",
+        "",
+        &context,
+        NodeOptions::new()
+            .use_global_arguments(false)
+            .arguments(local_arguments),
+    )
+    .unwrap();
+    assert_eq!(topic_name(&local_node), "/local");
+}
@@ -0,0 +1,32 @@
+// These tests need a ROS 2 installation, they are run by `colcon test`
+
+use rclrs::{deserialize_message, serialize_message, SerializedMessage};
+
+#[test]
+fn test_message_round_trip() {
+    let message = std_msgs::msg::String {
+        data: "Hello, world!".to_string(),
+    };
+
+    let serialized_message = serialize_message(&message).unwrap();
+    assert!(!serialized_message.is_empty());
+    let deserialized_message: std_msgs::msg::String =
+        deserialize_message(&serialized_message).unwrap();
+
+    assert_eq!(deserialized_message.data, message.data);
+}
+
+#[test]
+fn test_round_trip_through_bytes() {
+    let message = std_msgs::msg::String {
+        data: "Hello, bytes!".to_string(),
+    };
+
+    // E.g. what a custom transport would carry
+    let bytes = serialize_message(&message).unwrap().as_bytes().to_vec();
+    let serialized_message = SerializedMessage::from_bytes(&bytes).unwrap();
+    let deserialized_message: std_msgs::msg::String =
+        deserialize_message(&serialized_message).unwrap();
+
+    assert_eq!(deserialized_message.data, message.data);
+}
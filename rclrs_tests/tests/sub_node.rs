@@ -0,0 +1,22 @@
+// These tests need a ROS 2 installation, they are run by `colcon test`
+
+use rclrs::{Context, Node, QOS_PROFILE_DEFAULT};
+use std::vec::Vec;
+
+#[test]
+fn test_sub_node_prefixes_topics() {
+    let context = Context::builder(Vec::new()).build().unwrap();
+    let node = Node::new_with_namespace("base", "/robot", &context).unwrap();
+    let wheels = node.create_sub_node("wheels").unwrap();
+    let left = wheels.create_sub_node("left").unwrap();
+
+    let topic_name = |node: &Node, topic: &str| {
+        node.create_publisher::<std_msgs::msg::String>(topic, QOS_PROFILE_DEFAULT)
+            .unwrap()
+            .topic_name()
+    };
+    assert_eq!(topic_name(&wheels, "topic"), "/robot/wheels/topic");
+    assert_eq!(topic_name(&left, "topic"), "/robot/wheels/left/topic");
+    // Absolute names are not prefixed
+    assert_eq!(topic_name(&wheels, "/topic"), "/topic");
+}
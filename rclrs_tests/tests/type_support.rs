@@ -0,0 +1,30 @@
+// These tests need a ROS 2 installation, they are run by `colcon test`
+
+use rclrs::{lookup_type_support, TypeSupportError};
+
+#[test]
+fn test_lookup_known_type() {
+    let handle = lookup_type_support("std_msgs/msg/String").unwrap();
+    assert_ne!(handle.get(), 0);
+
+    // The second lookup is served from the registry
+    assert_eq!(lookup_type_support("std_msgs/msg/String"), Ok(handle));
+}
+
+#[test]
+fn test_lookup_missing_type() {
+    assert_eq!(
+        lookup_type_support("std_msgs/msg/NoSuchType"),
+        Err(TypeSupportError::TypeNotFound(
+            "std_msgs/msg/NoSuchType".to_string()
+        ))
+    );
+}
+
+#[test]
+fn test_lookup_missing_package() {
+    assert_eq!(
+        lookup_type_support("no_such_pkg/msg/X"),
+        Err(TypeSupportError::LibraryNotFound("no_such_pkg".to_string()))
+    );
+}
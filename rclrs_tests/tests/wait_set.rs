@@ -0,0 +1,104 @@
+// These tests need a ROS 2 installation, they are run by `colcon test`
+
+use rclrs::wait::WaitableKind;
+use rclrs::{Context, SubscriptionEventCallback, QOS_PROFILE_DEFAULT};
+use std::vec::Vec;
+
+// Keeps the tests away from other ROS applications running on the same machine
+const TEST_DOMAIN_ID: usize = 43;
+
+#[test]
+fn test_spin_once_described_counts_entities() {
+    let context = Context::builder(Vec::new())
+        .domain_id(TEST_DOMAIN_ID)
+        .build()
+        .unwrap();
+    let node = context.create_node("wait_set_description").unwrap();
+
+    let first_subscription = node
+        .create_subscription::<std_msgs::msg::String, _>(
+            "wait_set_first",
+            QOS_PROFILE_DEFAULT,
+            |_: &std_msgs::msg::String| {},
+        )
+        .unwrap();
+    let _second_subscription = node
+        .create_subscription::<std_msgs::msg::String, _>(
+            "wait_set_second",
+            QOS_PROFILE_DEFAULT,
+            |_: &std_msgs::msg::String| {},
+        )
+        .unwrap();
+    let _event = node
+        .create_subscription_event(
+            &first_subscription,
+            SubscriptionEventCallback::RequestedDeadlineMissed(Box::new(|_| {})),
+        )
+        .unwrap();
+
+    // Nothing is published, so this times out, which still describes the wait set
+    let description = rclrs::spin_once_described(&node, 10_000_000).unwrap();
+
+    assert_eq!(description.capacity.subscriptions, 2);
+    assert_eq!(description.capacity.events, 1);
+    assert_eq!(description.added.subscriptions, 2);
+    assert_eq!(description.added.events, 1);
+    let count_entries = |kind| {
+        description
+            .entries
+            .iter()
+            .filter(|entry| entry.kind == kind)
+            .count()
+    };
+    assert_eq!(count_entries(WaitableKind::Subscription), 2);
+    assert_eq!(count_entries(WaitableKind::Event), 1);
+    assert!(description.entries.iter().all(|entry| !entry.ready));
+}
+
+#[test]
+fn test_dropped_entities_leave_the_wait_set() {
+    let context = Context::builder(Vec::new())
+        .domain_id(TEST_DOMAIN_ID)
+        .build()
+        .unwrap();
+    let node = context.create_node("wait_set_pruning").unwrap();
+
+    let _live_subscription = node
+        .create_subscription::<std_msgs::msg::String, _>(
+            "wait_set_live",
+            QOS_PROFILE_DEFAULT,
+            |_: &std_msgs::msg::String| {},
+        )
+        .unwrap();
+
+    for _ in 0..5 {
+        let dropped_subscription = node
+            .create_subscription::<std_msgs::msg::String, _>(
+                "wait_set_dropped",
+                QOS_PROFILE_DEFAULT,
+                |_: &std_msgs::msg::String| {},
+            )
+            .unwrap();
+        let dropped_event = node
+            .create_subscription_event(
+                &dropped_subscription,
+                SubscriptionEventCallback::RequestedDeadlineMissed(Box::new(|_| {})),
+            )
+            .unwrap();
+
+        let description = rclrs::spin_once_described(&node, 10_000_000).unwrap();
+        assert_eq!(description.capacity.subscriptions, 2);
+        assert_eq!(description.added.subscriptions, 2);
+        assert_eq!(description.capacity.events, 1);
+        assert_eq!(description.added.events, 1);
+
+        drop(dropped_event);
+        drop(dropped_subscription);
+
+        let description = rclrs::spin_once_described(&node, 10_000_000).unwrap();
+        assert_eq!(description.capacity.subscriptions, 1);
+        assert_eq!(description.added.subscriptions, 1);
+        assert_eq!(description.capacity.events, 0);
+        assert_eq!(description.added.events, 0);
+    }
+}